@@ -0,0 +1,82 @@
+use std::fmt::{self, Display};
+
+use async_compression::tokio::bufread::{BzDecoder, GzipDecoder, XzDecoder, ZstdDecoder};
+use tokio::io::{self, AsyncRead, BufReader};
+
+use crate::format::peek;
+
+/// A compression format of an input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Zstd,
+    Xz,
+    Bzip2,
+}
+
+impl Compression {
+    /// Detect the compression format from the leading bytes of an input.
+    pub fn detect(bytes: &[u8]) -> Self {
+        if bytes.starts_with(&[0x1f, 0x8b]) {
+            Self::Gzip
+        } else if bytes.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Self::Zstd
+        } else if bytes.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+            Self::Xz
+        } else if bytes.starts_with(b"BZh") {
+            Self::Bzip2
+        } else {
+            Self::None
+        }
+    }
+}
+
+/// Detect the compression format of the reader and decompress it on the fly.
+///
+/// Uncompressed inputs are returned as they are.
+/// Compressed inputs may consist of multiple members, which also covers BGZF as well as
+/// concatenated zstd frames, xz streams and bzip2 streams.
+pub async fn decompress<'reader>(
+    reader: impl 'reader + AsyncRead + Unpin + Send,
+) -> io::Result<(Compression, Box<dyn 'reader + AsyncRead + Unpin + Send>)> {
+    let (prefix, reader) = peek(reader, 6).await?;
+    let compression = Compression::detect(&prefix);
+    let reader = BufReader::new(reader);
+
+    Ok(match compression {
+        Compression::None => (compression, Box::new(reader)),
+        Compression::Gzip => {
+            let mut decoder = GzipDecoder::new(reader);
+            decoder.multiple_members(true);
+            (compression, Box::new(decoder))
+        }
+        Compression::Zstd => {
+            let mut decoder = ZstdDecoder::new(reader);
+            decoder.multiple_members(true);
+            (compression, Box::new(decoder))
+        }
+        Compression::Xz => {
+            let mut decoder = XzDecoder::new(reader);
+            decoder.multiple_members(true);
+            (compression, Box::new(decoder))
+        }
+        Compression::Bzip2 => {
+            let mut decoder = BzDecoder::new(reader);
+            decoder.multiple_members(true);
+            (compression, Box::new(decoder))
+        }
+    })
+}
+
+impl Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::None => write!(f, "none"),
+            Self::Gzip => write!(f, "gzip"),
+            Self::Zstd => write!(f, "zstd"),
+            Self::Xz => write!(f, "xz"),
+            Self::Bzip2 => write!(f, "bzip2"),
+        }
+    }
+}
@@ -0,0 +1,876 @@
+use std::{
+    io::SeekFrom,
+    mem::take,
+    path::{Component, Path, PathBuf},
+    pin::Pin,
+    sync::Arc,
+};
+
+use async_zip::{
+    base::read::stream::ZipFileReader as ZipStreamReader, tokio::read::seek::ZipFileReader,
+};
+use log::debug;
+use tokio::{
+    fs::File,
+    io::{self, AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncSeekExt, BufReader, stdin},
+    sync::Semaphore,
+    task::{JoinError, JoinSet},
+};
+use tokio_stream::StreamExt;
+use tokio_tar::Archive;
+use tokio_util::compat::FuturesAsyncReadCompatExt;
+
+use crate::{
+    async_file::AsyncFile,
+    compression::{Compression, decompress},
+    counts::{CountOptions, KmerCounts},
+    format::{DETECTION_LENGTH, Format, peek},
+    progress::ProgressReader,
+    report::FileReport,
+    skip::SkipReason,
+    walk::{Walk, WalkEntry},
+};
+
+/// The path that stands for standard input.
+pub const STDIN_PATH: &str = "-";
+
+/// Counts the k-mers of files, directories and archives.
+///
+/// Cloning a counter is cheap, and clones share the limit on the number of files counted at the same time.
+#[derive(Debug, Clone)]
+pub struct KmerCounter {
+    options: Arc<CountOptions>,
+    jobs: usize,
+    file_permits: Arc<Semaphore>,
+}
+
+impl KmerCounter {
+    /// Create a counter that counts at most `jobs` files at the same time.
+    pub fn new(options: CountOptions, jobs: usize) -> Self {
+        Self {
+            options: Arc::new(options),
+            jobs: jobs.max(1),
+            file_permits: Arc::new(Semaphore::new(jobs.max(1))),
+        }
+    }
+
+    pub fn options(&self) -> &CountOptions {
+        &self.options
+    }
+
+    /// Count the given paths concurrently, and sum up their counts, keeping the reports in the given order.
+    ///
+    /// Directories are walked recursively, counting each file only once even if it is reachable by several paths.
+    /// Inputs that cannot be counted are recorded in [`KmerCounts::skipped`].
+    pub async fn count_paths(&self, paths: Vec<PathBuf>) -> KmerCounts {
+        let entries = Walk::new(&self.options).walk(paths).await;
+        if let Some(progress) = &self.options.progress {
+            for entry in &entries {
+                if let WalkEntry::File { size, .. } = entry {
+                    progress.add_total_file(*size);
+                }
+            }
+        }
+
+        // Only start as many tasks as there are jobs, and add up the counts of each file as soon as it is finished,
+        // such that the counts of finished files, which may contain all of their k-mers, do not pile up.
+        let mut sum = Sum::new(&self.options);
+        let mut tasks = JoinSet::new();
+        for (index, entry) in entries.into_iter().enumerate() {
+            if tasks.len() >= self.jobs {
+                sum.add(tasks.join_next().await.unwrap());
+            }
+
+            let counter = self.clone();
+            tasks.spawn(async move {
+                let counts = match entry {
+                    WalkEntry::File { path, is_input, .. } => {
+                        counter.count_walked_file(&path, is_input).await
+                    }
+                    WalkEntry::Skipped(path, reason) => {
+                        KmerCounts::skipped(&path, reason, &counter.options)
+                    }
+                };
+                (index, counts)
+            });
+        }
+
+        while let Some(result) = tasks.join_next().await {
+            sum.add(result);
+        }
+        sum.finish()
+    }
+
+    /// Count a file or, recursively, a directory.
+    /// The path [`STDIN_PATH`] stands for standard input.
+    ///
+    /// Inputs that cannot be counted are recorded in [`KmerCounts::skipped`].
+    pub async fn count_path(&self, path: &Path) -> KmerCounts {
+        self.count_paths(vec![path.to_owned()]).await
+    }
+
+    /// Count a file found by walking the input paths.
+    ///
+    /// Input paths are counted even if they do not match the include patterns.
+    async fn count_walked_file(&self, path: &Path, is_input: bool) -> KmerCounts {
+        let result = if path == Path::new(STDIN_PATH) {
+            self.count_reader(path, self.track_progress(stdin())).await
+        } else {
+            let _permit = self.file_permits.acquire().await;
+
+            match File::open(path).await {
+                Ok(file) => self.count_opened_file(path, file, !is_input).await,
+                Err(_) if !is_input && !self.options.filter.is_included(path) => {
+                    debug!("file {} is not included", path.display());
+                    Ok(KmerCounts::new(&self.options))
+                }
+                Err(error) => Err(error.into()),
+            }
+        };
+
+        if let Some(progress) = &self.options.progress {
+            progress.finish_file();
+        }
+
+        let mut sum = KmerCounts::new(&self.options);
+        sum.add_result(path, result);
+        sum
+    }
+
+    fn track_progress<R>(&self, reader: R) -> ProgressReader<R> {
+        ProgressReader::new(reader, self.options.progress.clone())
+    }
+
+    /// Count an opened file, reading it exactly once unless it is a regular file.
+    ///
+    /// Named pipes, process substitutions and character devices cannot be seeked,
+    /// so they are counted as a stream even if they contain a zip archive.
+    async fn count_opened_file(
+        &self,
+        path: &Path,
+        file: File,
+        apply_include: bool,
+    ) -> Result<KmerCounts, SkipReason> {
+        let is_file = file.metadata().await?.is_file();
+        let file = self.track_progress(file);
+        if is_file {
+            count_file(path, file, &self.options, apply_include).await
+        } else {
+            debug!("file {} is not seekable", path.display());
+            count_reader(path, file, &self.options, 0, apply_include).await
+        }
+    }
+
+    /// Count a file that may be compressed, and may be an archive.
+    ///
+    /// The path is only used to name the file and the entries of archives in reports.
+    /// Entries of archives that cannot be counted are recorded in [`KmerCounts::skipped`].
+    pub async fn count_file(
+        &self,
+        path: &Path,
+        file: impl AsyncFile,
+    ) -> Result<KmerCounts, SkipReason> {
+        count_file(path, file, &self.options, false).await
+    }
+
+    /// Like [`count_file`](Self::count_file), but for a reader that cannot be seeked.
+    pub async fn count_reader(
+        &self,
+        path: &Path,
+        reader: impl AsyncRead + Unpin + Send,
+    ) -> Result<KmerCounts, SkipReason> {
+        count_reader(path, reader, &self.options, 0, false).await
+    }
+
+    /// Count the records of an uncompressed fasta file.
+    pub async fn count_fasta_file(
+        &self,
+        path: &Path,
+        reader: impl AsyncBufRead + Unpin + Send,
+    ) -> Result<KmerCounts, SkipReason> {
+        count_fasta_file(path, reader, &self.options).await
+    }
+
+    /// Count the records of an uncompressed fastq file.
+    pub async fn count_fastq_file(
+        &self,
+        path: &Path,
+        reader: impl AsyncBufRead + Unpin + Send,
+    ) -> Result<KmerCounts, SkipReason> {
+        count_fastq_file(path, reader, &self.options).await
+    }
+}
+
+/// The sum of the counts of walked files, which may finish in any order.
+///
+/// The reports of each file are kept apart, such that they can be put in walk order at the end.
+struct Sum {
+    counts: KmerCounts,
+    reports: Vec<(usize, KmerCounts)>,
+}
+
+impl Sum {
+    fn new(options: &CountOptions) -> Self {
+        Self {
+            counts: KmerCounts::new(options),
+            reports: Vec::new(),
+        }
+    }
+
+    /// Add the counts of the file with the given walk index.
+    fn add(&mut self, result: Result<(usize, KmerCounts), JoinError>) {
+        let (index, mut counts) = result.expect("counting task panicked");
+        let reports = KmerCounts {
+            records: 0,
+            bases: 0,
+            by_k: Vec::new(),
+            files: take(&mut counts.files),
+            record_reports: take(&mut counts.record_reports),
+            skipped: take(&mut counts.skipped),
+        };
+        self.counts += counts;
+        self.reports.push((index, reports));
+    }
+
+    fn finish(mut self) -> KmerCounts {
+        self.reports.sort_unstable_by_key(|(index, _)| *index);
+        for (_, reports) in self.reports {
+            self.counts += reports;
+        }
+        self.counts
+    }
+}
+
+async fn count_file(
+    path: &Path,
+    mut file: impl AsyncFile,
+    options: &CountOptions,
+    apply_include: bool,
+) -> Result<KmerCounts, SkipReason> {
+    // Zip archives are read from their end, so they are counted directly from the file instead of a stream.
+    let prefix = async {
+        let (prefix, _) = peek(&mut file, DETECTION_LENGTH).await?;
+        file.seek(SeekFrom::Start(0)).await?;
+        io::Result::Ok(prefix)
+    }
+    .await;
+    let prefix = match prefix {
+        Err(_) if apply_include && !options.filter.is_included(path) => {
+            debug!("file {} is not included", path.display());
+            return Ok(KmerCounts::new(options));
+        }
+        prefix => prefix?,
+    };
+
+    if Format::detect(&prefix) == Format::Zip {
+        if options.max_archive_depth == 0 {
+            return Err(SkipReason::TooDeeplyNested {
+                max_archive_depth: options.max_archive_depth,
+            });
+        }
+        count_zip_file(path, &mut file, options, 0).await
+    } else {
+        count_reader(path, file, options, 0, apply_include).await
+    }
+}
+
+fn count_tar_file<'result>(
+    path: &'result Path,
+    file: Box<dyn 'result + AsyncRead + Unpin + Send>,
+    options: &'result CountOptions,
+    depth: usize,
+) -> Pin<Box<dyn 'result + Future<Output = Result<KmerCounts, SkipReason>> + Send>> {
+    Box::pin(async move {
+        let mut archive = Archive::new(file);
+        let mut entries = archive.entries()?;
+        let mut sum = KmerCounts::new(options);
+
+        while let Some(file) = entries.next().await {
+            let file = match file {
+                Ok(file) => file,
+                Err(error) => {
+                    // The remaining entries cannot be found anymore.
+                    sum.skip(path, error.into());
+                    break;
+                }
+            };
+            let path = entry_path(path, &file.path().unwrap_or_default());
+            // Directories, links and other special entries contain no data to count.
+            if !file.header().entry_type().is_file() {
+                debug!("entry {} is not a regular file", path.display());
+                continue;
+            }
+            if options.filter.is_excluded(&path) {
+                debug!("file {} is excluded", path.display());
+                continue;
+            }
+            sum.add_result(
+                &path,
+                count_reader(&path, file, options, depth + 1, true).await,
+            );
+        }
+
+        debug!("archive {} contains {sum}", path.display());
+        Ok(sum)
+    })
+}
+
+async fn count_zip_file(
+    path: &Path,
+    file: &mut impl AsyncFile,
+    options: &CountOptions,
+    depth: usize,
+) -> Result<KmerCounts, SkipReason> {
+    let mut archive = ZipFileReader::with_tokio(BufReader::new(file))
+        .await
+        .map_err(|error| SkipReason::malformed(Format::Zip, error))?;
+    let mut sum = KmerCounts::new(options);
+
+    for index in 0..archive.file().entries().len() {
+        let entry = &archive.file().entries()[index];
+        if entry.dir().unwrap_or_default() {
+            continue;
+        }
+
+        let path = entry_path(
+            path,
+            Path::new(entry.filename().as_str().unwrap_or_default()),
+        );
+        if options.filter.is_excluded(&path) {
+            debug!("file {} is excluded", path.display());
+            continue;
+        }
+        let result = match archive.reader_without_entry(index).await {
+            Ok(file) => count_reader(&path, file.compat(), options, depth + 1, true).await,
+            Err(_) if !options.filter.is_included(&path) => {
+                debug!("file {} is not included", path.display());
+                continue;
+            }
+            Err(error) => Err(SkipReason::malformed(Format::Zip, error)),
+        };
+        sum.add_result(&path, result);
+    }
+
+    debug!("archive {} contains {sum}", path.display());
+    Ok(sum)
+}
+
+/// Count the k-mers of a zip archive that cannot be seeked, e.g. because it is inside another archive.
+fn count_zip_stream<'result>(
+    path: &'result Path,
+    file: Box<dyn 'result + AsyncRead + Unpin + Send>,
+    options: &'result CountOptions,
+    depth: usize,
+) -> Pin<Box<dyn 'result + Future<Output = Result<KmerCounts, SkipReason>> + Send>> {
+    Box::pin(async move {
+        let mut archive = ZipStreamReader::with_tokio(BufReader::new(file));
+        let mut sum = KmerCounts::new(options);
+
+        loop {
+            let mut entry = match archive.next_with_entry().await {
+                Ok(Some(entry)) => entry,
+                Ok(None) => break,
+                Err(error) => {
+                    // The remaining entries cannot be found anymore.
+                    sum.skip(path, SkipReason::malformed(Format::Zip, error));
+                    break;
+                }
+            };
+
+            let entry_path = entry_path(
+                path,
+                Path::new(
+                    entry
+                        .reader()
+                        .entry()
+                        .filename()
+                        .as_str()
+                        .unwrap_or_default(),
+                ),
+            );
+            if entry.reader().entry().dir().unwrap_or_default() {
+                // Directory entries contain no data.
+            } else if options.filter.is_excluded(&entry_path) {
+                debug!("file {} is excluded", entry_path.display());
+            } else {
+                let result = count_reader(
+                    &entry_path,
+                    entry.reader_mut().compat(),
+                    options,
+                    depth + 1,
+                    true,
+                )
+                .await;
+                sum.add_result(&entry_path, result);
+            }
+
+            archive = match entry.skip().await {
+                Ok(archive) => archive,
+                Err(error) => {
+                    sum.skip(path, SkipReason::malformed(Format::Zip, error));
+                    break;
+                }
+            };
+        }
+
+        debug!("archive {} contains {sum}", path.display());
+        Ok(sum)
+    })
+}
+
+/// Returns the path of an archive entry inside the archive.
+///
+/// Leading `/` and `.` components of the entry path are dropped, such as the `./` that `tar -C dir .` writes,
+/// such that patterns and reports see the same path however the archive was created.
+fn entry_path(archive: &Path, entry: &Path) -> PathBuf {
+    let mut path = archive.to_owned();
+    path.extend(
+        entry
+            .components()
+            .filter(|component| matches!(component, Component::Normal(_) | Component::ParentDir)),
+    );
+    path
+}
+
+/// Count the k-mers of a file, detecting its format from its leading bytes.
+///
+/// The file may be compressed, and may be an archive.
+/// The depth is the number of archives that contain the file.
+/// If `apply_include` is set, files that are not archives are only counted if they match the include patterns.
+/// Other files are only read to detect archives, and are not counted instead of skipped if that fails.
+async fn count_reader(
+    path: &Path,
+    file: impl AsyncRead + Unpin + Send,
+    options: &CountOptions,
+    depth: usize,
+    apply_include: bool,
+) -> Result<KmerCounts, SkipReason> {
+    if let Some(progress) = &options.progress {
+        progress.set_current(path);
+    }
+
+    let detected = async {
+        let (compression, file) = decompress(file).await?;
+        let (prefix, file) = peek(file, DETECTION_LENGTH).await?;
+        io::Result::Ok((compression, prefix, file))
+    }
+    .await;
+
+    // Archives are always opened, such that the include patterns can select the files inside them.
+    if apply_include
+        && !options.filter.is_included(path)
+        && !detected
+            .as_ref()
+            .is_ok_and(|(_, prefix, _)| matches!(Format::detect(prefix), Format::Tar | Format::Zip))
+    {
+        debug!("file {} is not included", path.display());
+        return Ok(KmerCounts::new(options));
+    }
+    let (compression, prefix, file) = detected?;
+
+    if compression != Compression::None {
+        debug!("file {} is {compression} compressed", path.display());
+    }
+    if prefix.is_empty() {
+        debug!("file {} is empty", path.display());
+        return Ok(KmerCounts::new(options));
+    }
+
+    let format = Format::detect(&prefix);
+
+    match format {
+        Format::Tar | Format::Zip if depth >= options.max_archive_depth => {
+            Err(SkipReason::TooDeeplyNested {
+                max_archive_depth: options.max_archive_depth,
+            })
+        }
+        Format::Tar => count_tar_file(path, Box::new(file), options, depth).await,
+        Format::Zip => count_zip_stream(path, Box::new(file), options, depth).await,
+        format @ (Format::Fasta | Format::Fastq) => {
+            count_sequence_file(path, file, format, compression, options).await
+        }
+        Format::Unknown => Err(SkipReason::UnknownFormat),
+    }
+}
+
+/// Count the k-mers of a fasta or fastq file.
+async fn count_sequence_file(
+    path: &Path,
+    file: impl AsyncRead + Unpin + Send,
+    format: Format,
+    compression: Compression,
+    options: &CountOptions,
+) -> Result<KmerCounts, SkipReason> {
+    let reader = BufReader::with_capacity(1024 * 1024, file);
+
+    let mut counts = if format == Format::Fastq {
+        count_fastq_file(path, reader, options).await
+    } else {
+        count_fasta_file(path, reader, options).await
+    }?;
+
+    if options.report_files {
+        let report = FileReport::new(path, format, compression, &counts);
+        counts.files.push(report);
+    }
+    Ok(counts)
+}
+
+async fn count_fasta_file(
+    path: &Path,
+    mut reader: impl AsyncBufRead + Unpin + Send,
+    options: &CountOptions,
+) -> Result<KmerCounts, SkipReason> {
+    let mut sum = KmerCounts::new(options);
+    let record_path: Arc<Path> = path.into();
+    // Without options that need the characters, only the length of each record is counted,
+    // such that long records are not held in memory.
+    let keep_sequence = options.needs_sequence();
+    let mut header = Vec::new();
+    let mut sequence = Vec::new();
+
+    // Skip to entry header start.
+    let mut last_is_newline = true;
+    while let Some(b) = read_byte(&mut reader).await? {
+        if last_is_newline && b == b'>' {
+            // Found entry header.
+            break;
+        }
+
+        last_is_newline = b == b'\n' || b == b'\r';
+    }
+
+    loop {
+        // Read entry header.
+        header.clear();
+        let mut has_characters = false;
+        while let Some(b) = read_byte(&mut reader).await? {
+            has_characters = true;
+            if b == b'\n' || b == b'\r' {
+                break;
+            }
+            header.push(b);
+        }
+
+        if !has_characters {
+            break;
+        }
+
+        // Collect entry characters.
+        sequence.clear();
+        let mut length = 0;
+        let mut last_is_newline = true;
+        while let Some(b) = read_byte(&mut reader).await? {
+            if b == b'>' {
+                if last_is_newline {
+                    // Found entry header.
+                    break;
+                } else {
+                    // Found entry header character  without preceding newline.
+                    return Err(SkipReason::malformed(
+                        Format::Fasta,
+                        "entry header does not start on a new line",
+                    ));
+                }
+            }
+
+            if b != b'\n' && b != b'\r' {
+                length += 1;
+                if keep_sequence {
+                    sequence.push(b);
+                }
+            }
+
+            last_is_newline = b == b'\n' || b == b'\r';
+        }
+
+        if keep_sequence {
+            sum.add_record(&record_path, &header, &sequence, options);
+        } else {
+            sum.add_record_length(&record_path, &header, length, options);
+        }
+    }
+
+    debug!("file {} contains {sum}", path.display());
+    Ok(sum)
+}
+
+async fn count_fastq_file(
+    path: &Path,
+    mut reader: impl AsyncBufRead + Unpin + Send,
+    options: &CountOptions,
+) -> Result<KmerCounts, SkipReason> {
+    let mut sum = KmerCounts::new(options);
+    let record_path: Arc<Path> = path.into();
+    // Like for fasta files, the lines of a record are only held in memory if their characters are needed.
+    let keep_sequence = options.needs_sequence();
+    let mut header = Vec::new();
+    let mut sequence = Vec::new();
+    let mut line = Vec::new();
+
+    // Read the first entry header, skipping empty lines.
+    read_non_empty_line(&mut reader, &mut line).await?;
+
+    while !line.is_empty() {
+        if line.first() != Some(&b'@') {
+            return Err(SkipReason::malformed(
+                Format::Fastq,
+                "entry header does not start with '@'",
+            ));
+        }
+        header.clear();
+        header.extend_from_slice(&line[1..]);
+
+        // Collect entry characters until the separator line.
+        sequence.clear();
+        let mut length = 0;
+        loop {
+            let mut is_separator = None;
+            let found = read_line_parts(&mut reader, |part| {
+                if !*is_separator.get_or_insert(part.first() == Some(&b'+')) {
+                    length += part.len();
+                    if keep_sequence {
+                        sequence.extend_from_slice(part);
+                    }
+                }
+            })
+            .await?;
+            if !found {
+                return Err(SkipReason::malformed(
+                    Format::Fastq,
+                    "entry ends before its separator line",
+                ));
+            }
+            if is_separator == Some(true) {
+                break;
+            }
+        }
+
+        // Skip quality characters, which may span multiple lines like the sequence.
+        let mut quality_length = 0;
+        while quality_length < length {
+            let mut line_length = 0;
+            let found = read_line_parts(&mut reader, |part| line_length += part.len()).await?;
+            if !found || line_length == 0 {
+                return Err(SkipReason::malformed(
+                    Format::Fastq,
+                    "quality is shorter than the sequence",
+                ));
+            }
+            quality_length += line_length;
+        }
+
+        if keep_sequence {
+            sum.add_record(&record_path, &header, &sequence, options);
+        } else {
+            sum.add_record_length(&record_path, &header, length, options);
+        }
+
+        // Read the next entry header, skipping empty lines.
+        read_non_empty_line(&mut reader, &mut line).await?;
+    }
+
+    debug!("file {} contains {sum}", path.display());
+    Ok(sum)
+}
+
+/// Read a single byte.
+///
+/// Returns `None` if the reader is at its end.
+async fn read_byte(reader: &mut (impl AsyncBufRead + Unpin + Send)) -> io::Result<Option<u8>> {
+    // Unlike `read_u8`, this keeps the end of the reader apart from errors of e.g. a truncated compressed file.
+    let Some(&b) = reader.fill_buf().await?.first() else {
+        return Ok(None);
+    };
+    reader.consume(1);
+    Ok(Some(b))
+}
+
+/// Read a line without its line terminator.
+///
+/// Returns false if the reader is at its end.
+async fn read_line(
+    reader: &mut (impl AsyncBufRead + Unpin + Send),
+    line: &mut Vec<u8>,
+) -> io::Result<bool> {
+    line.clear();
+    if reader.read_until(b'\n', line).await? == 0 {
+        return Ok(false);
+    }
+
+    while matches!(line.last(), Some(b'\n' | b'\r')) {
+        line.pop();
+    }
+    Ok(true)
+}
+
+/// Read a line without holding all of it in memory,
+/// passing its non-empty parts between line terminator characters to `part` in order.
+///
+/// Returns false if the reader is at its end.
+async fn read_line_parts(
+    reader: &mut (impl AsyncBufRead + Unpin + Send),
+    mut part: impl FnMut(&[u8]),
+) -> io::Result<bool> {
+    let mut found = false;
+    loop {
+        let buffer = reader.fill_buf().await?;
+        if buffer.is_empty() {
+            return Ok(found);
+        }
+        found = true;
+
+        let end = buffer.iter().position(|&b| b == b'\n');
+        let line = &buffer[..end.unwrap_or(buffer.len())];
+        line.split(|&b| b == b'\r')
+            .filter(|line_part| !line_part.is_empty())
+            .for_each(&mut part);
+
+        match end {
+            Some(end) => {
+                reader.consume(end + 1);
+                return Ok(true);
+            }
+            None => {
+                let length = buffer.len();
+                reader.consume(length);
+            }
+        }
+    }
+}
+
+/// Read the next line that is not empty, without its line terminator.
+///
+/// If the reader is at its end, the line is left empty.
+async fn read_non_empty_line(
+    reader: &mut (impl AsyncBufRead + Unpin + Send),
+    line: &mut Vec<u8>,
+) -> io::Result<()> {
+    while read_line(reader, line).await? {
+        if !line.is_empty() {
+            return Ok(());
+        }
+    }
+    line.clear();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn count_fastq(input: &[u8]) -> Result<KmerCounts, SkipReason> {
+        let options = CountOptions::new(vec![3]);
+        count_fastq_file(Path::new("test.fq"), input, &options).await
+    }
+
+    fn assert_counts(counts: &KmerCounts, records: usize, bases: usize, kmers: usize) {
+        assert_eq!(counts.records, records);
+        assert_eq!(counts.bases, bases);
+        assert_eq!(counts.by_k[0].total, kmers);
+    }
+
+    fn assert_malformed(result: Result<KmerCounts, SkipReason>) {
+        assert!(
+            matches!(
+                result,
+                Err(SkipReason::Malformed {
+                    format: Format::Fastq,
+                    ..
+                })
+            ),
+            "{result:?}"
+        );
+    }
+
+    #[test]
+    fn entry_paths() {
+        let archive = Path::new("t.tar");
+        assert_eq!(
+            entry_path(archive, Path::new("./b.fq")),
+            Path::new("t.tar/b.fq")
+        );
+        assert_eq!(
+            entry_path(archive, Path::new("/x/./a.fa")),
+            Path::new("t.tar/x/a.fa")
+        );
+        assert_eq!(entry_path(archive, Path::new(".")), archive);
+    }
+
+    #[tokio::test]
+    async fn fastq_single_line() {
+        let counts = count_fastq(b"@r1\nACGTA\n+\nIIIII\n@r2\nAC\n+\nII\n")
+            .await
+            .unwrap();
+        assert_counts(&counts, 2, 7, 3);
+    }
+
+    #[tokio::test]
+    async fn fastq_multi_line_sequence_and_quality() {
+        let counts = count_fastq(b"@r1\nACG\nTA\n+\nII\nIII\n@r2\nACGT\n+\nIIII")
+            .await
+            .unwrap();
+        assert_counts(&counts, 2, 9, 5);
+    }
+
+    #[tokio::test]
+    async fn fastq_quality_starting_with_header_characters() {
+        let counts = count_fastq(b"@r1\nACGT\n+\n@@++\n@r2\nACGT\n+r2\n+I@I\n")
+            .await
+            .unwrap();
+        assert_counts(&counts, 2, 8, 4);
+    }
+
+    #[tokio::test]
+    async fn fastq_crlf_and_empty_lines() {
+        let counts =
+            count_fastq(b"\r\n@r1\r\nACGT\r\n+\r\nIIII\r\n\r\n@r2\r\nACG\r\n+\r\nIII\r\n\r\n")
+                .await
+                .unwrap();
+        assert_counts(&counts, 2, 7, 3);
+    }
+
+    #[tokio::test]
+    async fn fastq_lines_longer_than_buffer() {
+        let options = CountOptions::new(vec![3]);
+        let reader = BufReader::with_capacity(2, b"@r1\r\nACGTA\r\n+r1\r\nIIIII\r\n".as_slice());
+        let counts = count_fastq_file(Path::new("test.fq"), reader, &options)
+            .await
+            .unwrap();
+        assert_counts(&counts, 1, 5, 3);
+    }
+
+    #[tokio::test]
+    async fn fastq_extract_kmers() {
+        let mut options = CountOptions::new(vec![3]);
+        options.extract_kmers = true;
+        let input = b"@r1\nACG\nTA\n+\nII\nIII\n".as_slice();
+        let counts = count_fastq_file(Path::new("test.fq"), input, &options)
+            .await
+            .unwrap();
+        assert_counts(&counts, 1, 5, 3);
+        assert_eq!(counts.by_k[0].distinct_count(), Some(3));
+    }
+
+    #[tokio::test]
+    async fn fastq_empty() {
+        let counts = count_fastq(b"").await.unwrap();
+        assert_counts(&counts, 0, 0, 0);
+    }
+
+    #[tokio::test]
+    async fn fastq_malformed_header() {
+        assert_malformed(count_fastq(b"@r1\nACGT\n+\nIIII\nr2\nACGT\n+\nIIII\n").await);
+    }
+
+    #[tokio::test]
+    async fn fastq_missing_separator() {
+        assert_malformed(count_fastq(b"@r1\nACGT\n").await);
+    }
+
+    #[tokio::test]
+    async fn fastq_short_quality() {
+        assert_malformed(count_fastq(b"@r1\nACGT\n+\nII\n").await);
+        assert_malformed(count_fastq(b"@r1\nACGT\n+\nII\n\n@r2\nACGT\n+\nIIII\n").await);
+    }
+}
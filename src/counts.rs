@@ -0,0 +1,390 @@
+use std::{
+    collections::HashMap,
+    fmt::{self, Display},
+    ops::AddAssign,
+    path::Path,
+    sync::Arc,
+};
+
+use crate::{
+    filter::PathFilter,
+    kmer::{self, Alphabet, SpacedSeed},
+    minimizer::{MinimizerOptions, for_each_minimizer},
+    progress::Progress,
+    report::{FileReport, RecordReport},
+    skip::{SkipReason, SkippedInput},
+};
+
+/// Options that control how k-mers are extracted and counted.
+#[derive(Debug, Clone)]
+pub struct CountOptions {
+    /// The values of k to count k-mers for, in ascending order.
+    /// With a spaced seed, this is its span.
+    pub k: Vec<usize>,
+
+    /// If set, only the characters at the `1` positions of the seed are part of each k-mer.
+    pub seed: Option<SpacedSeed>,
+
+    /// If true, all k-mers are extracted and their occurrences counted individually.
+    pub extract_kmers: bool,
+
+    /// If true, each k-mer is identified with its reverse complement.
+    pub canonical: bool,
+
+    /// If set, k-mers containing characters outside of the alphabet are skipped.
+    pub alphabet: Option<Alphabet>,
+
+    /// If set, only the minimizers of each window of k-mers are counted, once per minimizer position.
+    pub minimizer: Option<MinimizerOptions>,
+
+    /// The maximum nesting depth of archives inside archives.
+    pub max_archive_depth: usize,
+
+    /// If true, symlinks inside directories are followed.
+    /// Symlinks given as input paths are always followed.
+    pub follow_symlinks: bool,
+
+    /// Selects the files in directories and the entries of archives to count.
+    pub filter: PathFilter,
+
+    /// If set, the progress of counting is recorded in it.
+    pub progress: Option<Arc<Progress>>,
+
+    /// If true, the counts of each file are recorded in [`KmerCounts::files`].
+    pub report_files: bool,
+
+    /// If true, the counts of each record are recorded in [`KmerCounts::record_reports`].
+    pub report_records: bool,
+}
+
+/// The k-mer counts of an input, for each k.
+#[derive(Debug)]
+pub struct KmerCounts {
+    /// The number of records, i.e. fasta or fastq entries.
+    pub records: usize,
+
+    /// The number of sequence characters over all records.
+    pub bases: usize,
+
+    /// The counts for each k, in the order of [`CountOptions::k`].
+    pub by_k: Vec<KCounts>,
+
+    /// The counts of each file, if file reports are enabled.
+    pub files: Vec<FileReport>,
+
+    /// The counts of each record, if record reports are enabled.
+    pub record_reports: Vec<RecordReport>,
+
+    /// The inputs that were skipped, with the reason why.
+    pub skipped: Vec<SkippedInput>,
+}
+
+/// The k-mer counts of an input for a single k.
+#[derive(Debug)]
+pub struct KCounts {
+    pub k: usize,
+
+    /// The number of k-mer occurrences, counting duplicates separately.
+    pub total: usize,
+
+    /// The occurrence count of each k-mer, if k-mer extraction is enabled.
+    pub kmers: Option<HashMap<Box<[u8]>, usize>>,
+}
+
+impl CountOptions {
+    /// Options that count all k-mer occurrences for the given values of k, without any reports.
+    pub fn new(mut k: Vec<usize>) -> Self {
+        k.sort_unstable();
+        k.dedup();
+
+        Self {
+            k,
+            seed: None,
+            extract_kmers: false,
+            canonical: false,
+            alphabet: None,
+            minimizer: None,
+            max_archive_depth: 4,
+            follow_symlinks: true,
+            filter: PathFilter::default(),
+            progress: None,
+            report_files: false,
+            report_records: false,
+        }
+    }
+
+    /// Options that count all spaced k-mers of the given seed, without any reports.
+    pub fn with_seed(seed: SpacedSeed) -> Self {
+        Self {
+            seed: Some(seed.clone()),
+            ..Self::new(vec![seed.span()])
+        }
+    }
+
+    /// Returns true if the characters of each record are needed, instead of only its length.
+    pub fn needs_sequence(&self) -> bool {
+        self.extract_kmers
+            || self.canonical
+            || self.seed.is_some()
+            || self.alphabet.is_some()
+            || self.minimizer.is_some()
+    }
+}
+
+impl KmerCounts {
+    pub fn new(options: &CountOptions) -> Self {
+        Self {
+            records: 0,
+            bases: 0,
+            by_k: options
+                .k
+                .iter()
+                .map(|&k| KCounts {
+                    k,
+                    total: 0,
+                    kmers: options.extract_kmers.then(HashMap::new),
+                })
+                .collect(),
+            files: Vec::new(),
+            record_reports: Vec::new(),
+            skipped: Vec::new(),
+        }
+    }
+
+    /// Returns counts that only record the given input as skipped.
+    pub fn skipped(path: &Path, reason: SkipReason, options: &CountOptions) -> Self {
+        let mut counts = Self::new(options);
+        counts.skip(path, reason);
+        counts
+    }
+
+    /// Add the counts of an input, or record it as skipped if it could not be counted.
+    pub fn add_result(&mut self, path: &Path, result: Result<Self, SkipReason>) {
+        match result {
+            Ok(counts) => *self += counts,
+            Err(reason) => self.skip(path, reason),
+        }
+    }
+
+    /// Record the given input as skipped.
+    pub fn skip(&mut self, path: &Path, reason: SkipReason) {
+        self.skipped.push(SkippedInput {
+            path: path.to_owned(),
+            reason,
+        });
+    }
+
+    /// Count the k-mers of a single fasta or fastq record.
+    ///
+    /// The path is shared by the reports of all records of a file.
+    pub fn add_record(
+        &mut self,
+        path: &Arc<Path>,
+        header: &[u8],
+        sequence: &[u8],
+        options: &CountOptions,
+    ) {
+        let kmers = self
+            .by_k
+            .iter_mut()
+            .map(|counts| (counts.k, counts.add_sequence(sequence, options)))
+            .collect();
+        self.finish_record(path, header, sequence.len(), kmers, options);
+    }
+
+    /// Count the k-mers of a single record of which only the length is known.
+    ///
+    /// This is only valid if [`CountOptions::needs_sequence`] is false.
+    pub fn add_record_length(
+        &mut self,
+        path: &Arc<Path>,
+        header: &[u8],
+        length: usize,
+        options: &CountOptions,
+    ) {
+        debug_assert!(!options.needs_sequence());
+        let kmers = self
+            .by_k
+            .iter_mut()
+            .map(|counts| {
+                let total = (length + 1).saturating_sub(counts.k);
+                counts.total += total;
+                (counts.k, total)
+            })
+            .collect();
+        self.finish_record(path, header, length, kmers, options);
+    }
+
+    fn finish_record(
+        &mut self,
+        path: &Arc<Path>,
+        header: &[u8],
+        length: usize,
+        kmers: Vec<(usize, usize)>,
+        options: &CountOptions,
+    ) {
+        self.records += 1;
+        self.bases += length;
+
+        if options.report_records {
+            self.record_reports
+                .push(RecordReport::new(path.clone(), header, length, kmers));
+        }
+    }
+}
+
+impl KCounts {
+    /// Count the k-mers of a single sequence.
+    ///
+    /// Returns the number of k-mer occurrences in the sequence.
+    pub fn add_sequence(&mut self, sequence: &[u8], options: &CountOptions) -> usize {
+        match (&options.alphabet, &options.seed) {
+            // Only the characters at the `1` positions of a seed are part of a k-mer,
+            // so the characters at its `0` positions may be outside of the alphabet.
+            (Some(alphabet), Some(seed)) => self.add_windows(
+                sequence
+                    .windows(self.k)
+                    .filter(|window| seed.selects_only(window, alphabet)),
+                options,
+            ),
+            (Some(alphabet), None) => sequence
+                .split(|character| !alphabet.contains(*character))
+                .map(|part| self.add_valid_sequence(part, options))
+                .sum(),
+            (None, _) => self.add_valid_sequence(sequence, options),
+        }
+    }
+
+    /// Count the k-mers of a sequence that contains only characters of the alphabet.
+    ///
+    /// Returns the number of k-mer occurrences in the sequence.
+    fn add_valid_sequence(&mut self, sequence: &[u8], options: &CountOptions) -> usize {
+        if let Some(minimizer) = &options.minimizer {
+            return self.add_minimizers(sequence, options.canonical, minimizer);
+        }
+
+        let k = self.k;
+        if self.kmers.is_none() || k == 0 {
+            let total = (sequence.len() + 1).saturating_sub(k);
+            self.total += total;
+            return total;
+        }
+
+        self.add_windows(sequence.windows(k), options)
+    }
+
+    /// Count the k-mers of windows of k characters.
+    ///
+    /// Returns the number of windows.
+    fn add_windows<'sequence>(
+        &mut self,
+        windows: impl Iterator<Item = &'sequence [u8]>,
+        options: &CountOptions,
+    ) -> usize {
+        let mut total = 0;
+        let mut buffer = Vec::with_capacity(self.k);
+        let mut reverse_buffer = Vec::with_capacity(self.k);
+        for window in windows {
+            total += 1;
+            let Some(kmers) = &mut self.kmers else {
+                continue;
+            };
+
+            let kmer = match (&options.seed, options.canonical) {
+                (Some(seed), true) => {
+                    seed.extract_canonical(window, &mut buffer, &mut reverse_buffer)
+                }
+                (Some(seed), false) => seed.extract(window, &mut buffer),
+                (None, true) => kmer::canonical(window, &mut buffer),
+                (None, false) => window,
+            };
+
+            if let Some(count) = kmers.get_mut(kmer) {
+                *count += 1;
+            } else {
+                kmers.insert(kmer.into(), 1);
+            }
+        }
+
+        self.total += total;
+        total
+    }
+
+    /// Count the minimizer positions of a sequence that contains only characters of the alphabet.
+    ///
+    /// Returns the number of minimizer positions in the sequence.
+    fn add_minimizers(
+        &mut self,
+        sequence: &[u8],
+        canonical: bool,
+        options: &MinimizerOptions,
+    ) -> usize {
+        let mut total = 0;
+        for_each_minimizer(sequence, self.k, canonical, options, |minimizer| {
+            total += 1;
+            if let Some(kmers) = &mut self.kmers {
+                if let Some(count) = kmers.get_mut(minimizer) {
+                    *count += 1;
+                } else {
+                    kmers.insert(minimizer.into(), 1);
+                }
+            }
+        });
+
+        self.total += total;
+        total
+    }
+
+    /// The number of distinct k-mers, if k-mer extraction is enabled.
+    pub fn distinct_count(&self) -> Option<usize> {
+        self.kmers.as_ref().map(HashMap::len)
+    }
+}
+
+impl AddAssign for KmerCounts {
+    fn add_assign(&mut self, rhs: Self) {
+        self.records += rhs.records;
+        self.bases += rhs.bases;
+
+        for (counts, rhs) in self.by_k.iter_mut().zip(rhs.by_k) {
+            *counts += rhs;
+        }
+
+        self.files.extend(rhs.files);
+        self.record_reports.extend(rhs.record_reports);
+        self.skipped.extend(rhs.skipped);
+    }
+}
+
+impl AddAssign for KCounts {
+    fn add_assign(&mut self, rhs: Self) {
+        debug_assert_eq!(self.k, rhs.k);
+        self.total += rhs.total;
+
+        match (&mut self.kmers, rhs.kmers) {
+            (Some(kmers), Some(mut rhs)) => {
+                // Merge the smaller map into the larger one.
+                if rhs.len() > kmers.len() {
+                    std::mem::swap(kmers, &mut rhs);
+                }
+                for (kmer, count) in rhs {
+                    *kmers.entry(kmer).or_default() += count;
+                }
+            }
+            (kmers @ None, rhs @ Some(_)) => *kmers = rhs,
+            (_, None) => {}
+        }
+    }
+}
+
+impl Display for KmerCounts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, counts) in self.by_k.iter().enumerate() {
+            if index > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{} {}-mers", counts.total, counts.k)?;
+        }
+        Ok(())
+    }
+}
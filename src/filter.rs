@@ -0,0 +1,133 @@
+use std::path::Path;
+
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+
+/// Selects the files in directories and the entries of archives to count by glob patterns.
+///
+/// A pattern without a `/` matches the file name, e.g. `*.fa*`.
+/// A pattern with a `/` matches the whole path, where `*` does not match `/`, but `**` matches any number of directories, e.g. `**/tmp/**`.
+#[derive(Debug, Clone, Default)]
+pub struct PathFilter {
+    /// If not empty, only files matching one of these patterns are counted.
+    include: Patterns,
+
+    /// Files and directories matching one of these patterns are not counted.
+    exclude: Patterns,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Patterns {
+    names: GlobSet,
+    paths: GlobSet,
+}
+
+impl PathFilter {
+    /// Create a filter from include and exclude patterns.
+    ///
+    /// If there are no include patterns, all paths that are not excluded are counted.
+    pub fn new(include: &[String], exclude: &[String]) -> Result<Self, globset::Error> {
+        Ok(Self {
+            include: Patterns::new(include)?,
+            exclude: Patterns::new(exclude)?,
+        })
+    }
+
+    /// Returns true if the file is selected by the include patterns.
+    ///
+    /// Archives should not be checked, such that the files inside them can be selected.
+    pub fn is_included(&self, path: &Path) -> bool {
+        self.include.is_empty() || self.include.is_match(path)
+    }
+
+    /// Returns true if the file or directory is matched by the exclude patterns.
+    pub fn is_excluded(&self, path: &Path) -> bool {
+        self.exclude.is_match(path)
+    }
+}
+
+impl Patterns {
+    fn new(patterns: &[String]) -> Result<Self, globset::Error> {
+        let mut names = GlobSetBuilder::new();
+        let mut paths = GlobSetBuilder::new();
+        for pattern in patterns {
+            let glob = GlobBuilder::new(pattern).literal_separator(true).build()?;
+            if pattern.contains('/') {
+                paths.add(glob);
+            } else {
+                names.add(glob);
+            }
+        }
+
+        Ok(Self {
+            names: names.build()?,
+            paths: paths.build()?,
+        })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.names.is_empty() && self.paths.is_empty()
+    }
+
+    fn is_match(&self, path: &Path) -> bool {
+        path.file_name()
+            .is_some_and(|name| self.names.is_match(name))
+            || self.paths.is_match(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(include: &[&str], exclude: &[&str]) -> PathFilter {
+        let patterns = |patterns: &[&str]| {
+            patterns
+                .iter()
+                .map(|pattern| pattern.to_string())
+                .collect::<Vec<_>>()
+        };
+        PathFilter::new(&patterns(include), &patterns(exclude)).unwrap()
+    }
+
+    #[test]
+    fn name_patterns() {
+        let filter = filter(&["*.fa*"], &[]);
+        assert!(filter.is_included(Path::new("x.fa")));
+        assert!(filter.is_included(Path::new("refs/x.fa.gz")));
+        assert!(filter.is_included(Path::new("t.tar/sub/x.fasta")));
+        assert!(!filter.is_included(Path::new("refs.fa_dir/notes.log")));
+    }
+
+    #[test]
+    fn path_patterns() {
+        let filter = filter(&[], &["**/tmp/**", "refs/*.fa"]);
+        assert!(filter.is_excluded(Path::new("tmp/x.fa")));
+        assert!(filter.is_excluded(Path::new("a/b/tmp/x.fa")));
+        assert!(filter.is_excluded(Path::new("refs/x.fa")));
+        // A `*` does not match a `/`, and patterns with a `/` do not match just the file name.
+        assert!(!filter.is_excluded(Path::new("refs/sub/x.fa")));
+        assert!(!filter.is_excluded(Path::new("other/refs/x.fa")));
+        assert!(!filter.is_excluded(Path::new("a/tmpfiles/x.fa")));
+    }
+
+    #[test]
+    fn excluded_directories() {
+        let filter = filter(&["*.fa"], &["tmp", "data/old"]);
+        assert!(filter.is_excluded(Path::new("data/tmp")));
+        assert!(filter.is_excluded(Path::new("data/old")));
+        assert!(!filter.is_excluded(Path::new("data")));
+        assert!(!filter.is_excluded(Path::new("data/older")));
+    }
+
+    #[test]
+    fn no_patterns() {
+        let filter = PathFilter::default();
+        assert!(filter.is_included(Path::new("x.log")));
+        assert!(!filter.is_excluded(Path::new("x.log")));
+    }
+
+    #[test]
+    fn invalid_pattern() {
+        assert!(PathFilter::new(&["[".to_string()], &[]).is_err());
+    }
+}
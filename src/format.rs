@@ -0,0 +1,207 @@
+use std::{
+    fmt::{self, Display},
+    io::Cursor,
+};
+
+use tokio::io::{self, AsyncRead, AsyncReadExt};
+
+/// The number of leading bytes required to detect the format of an input.
+pub const DETECTION_LENGTH: usize = TAR_HEADER_LENGTH;
+
+const TAR_HEADER_LENGTH: usize = 512;
+
+/// The format of an input, after decompression.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Tar,
+    Zip,
+    Fasta,
+    Fastq,
+    Unknown,
+}
+
+impl Format {
+    /// Detect the format from the leading bytes of an input.
+    ///
+    /// At least [`DETECTION_LENGTH`] bytes should be given, unless the input is shorter.
+    /// Fasta files may start with a preamble or `;` comment lines before their first entry header.
+    pub fn detect(prefix: &[u8]) -> Self {
+        if is_zip(prefix) {
+            Self::Zip
+        } else if is_tar(prefix) {
+            Self::Tar
+        } else if prefix.trim_ascii_start().starts_with(b"@") {
+            Self::Fastq
+        } else if prefix
+            .split(|&character| character == b'\n' || character == b'\r')
+            .any(|line| line.trim_ascii_start().starts_with(b">"))
+        {
+            Self::Fasta
+        } else {
+            Self::Unknown
+        }
+    }
+}
+
+/// Read up to `length` leading bytes of the reader without losing them.
+///
+/// Returns the leading bytes and a reader that still yields the complete input.
+/// If the input is shorter than `length`, all of it is returned.
+pub async fn peek<'reader>(
+    mut reader: impl 'reader + AsyncRead + Unpin + Send,
+    length: usize,
+) -> io::Result<(Vec<u8>, impl 'reader + AsyncRead + Unpin + Send)> {
+    let mut prefix = Vec::with_capacity(length);
+    (&mut reader)
+        .take(length as u64)
+        .read_to_end(&mut prefix)
+        .await?;
+
+    Ok((prefix.clone(), Cursor::new(prefix).chain(reader)))
+}
+
+/// Returns true if the leading bytes of an input are a tar header.
+///
+/// Headers of POSIX and GNU tar archives are recognised by their ustar magic,
+/// and headers of old-style tar archives by their checksum.
+/// An empty tar archive starts with the zero block that marks its end.
+fn is_tar(prefix: &[u8]) -> bool {
+    let Some(header) = prefix.get(..TAR_HEADER_LENGTH) else {
+        return false;
+    };
+    if header[257..262] == *b"ustar" || header.iter().all(|&byte| byte == 0) {
+        return true;
+    }
+
+    // The checksum is the sum of all header bytes, with the checksum field itself counted as spaces.
+    let Some(checksum) = std::str::from_utf8(&header[148..156])
+        .ok()
+        .map(|checksum| checksum.trim_matches(['\0', ' ']))
+        .and_then(|checksum| u32::from_str_radix(checksum, 8).ok())
+    else {
+        return false;
+    };
+    let sum: u32 = header
+        .iter()
+        .enumerate()
+        .map(|(index, &byte)| {
+            if (148..156).contains(&index) {
+                u32::from(b' ')
+            } else {
+                u32::from(byte)
+            }
+        })
+        .sum();
+
+    checksum == sum
+}
+
+/// Returns true if the leading bytes of an input are a zip local file header,
+/// or the end of central directory record of an empty zip archive.
+fn is_zip(prefix: &[u8]) -> bool {
+    prefix.starts_with(b"PK\x03\x04") || prefix.starts_with(b"PK\x05\x06")
+}
+
+impl Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tar => write!(f, "tar"),
+            Self::Zip => write!(f, "zip"),
+            Self::Fasta => write!(f, "fasta"),
+            Self::Fastq => write!(f, "fastq"),
+            Self::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::AsyncReadExt;
+
+    use super::*;
+
+    /// A tar header of a regular file with a valid checksum, with or without the ustar magic.
+    fn tar_header(ustar: bool) -> Vec<u8> {
+        let mut header = vec![0; TAR_HEADER_LENGTH];
+        header[..4].copy_from_slice(b"x.fa");
+        header[100..108].copy_from_slice(b"0000644\0");
+        header[124..136].copy_from_slice(b"00000000012\0");
+        header[156] = b'0';
+        if ustar {
+            header[257..265].copy_from_slice(b"ustar\x0000");
+        }
+
+        header[148..156].fill(b' ');
+        let checksum: u32 = header.iter().map(|&byte| u32::from(byte)).sum();
+        header[148..156].copy_from_slice(format!("{checksum:06o}\0 ").as_bytes());
+        header
+    }
+
+    #[test]
+    fn detect_ustar() {
+        assert_eq!(Format::detect(&tar_header(true)), Format::Tar);
+    }
+
+    #[test]
+    fn detect_old_tar() {
+        assert_eq!(Format::detect(&tar_header(false)), Format::Tar);
+    }
+
+    #[test]
+    fn detect_old_tar_with_wrong_checksum() {
+        let mut header = tar_header(false);
+        header[0] = b'>';
+        assert_eq!(Format::detect(&header), Format::Fasta);
+    }
+
+    #[test]
+    fn detect_empty_tar() {
+        assert_eq!(Format::detect(&[0; 2 * TAR_HEADER_LENGTH]), Format::Tar);
+        assert_eq!(Format::detect(&[0; 100]), Format::Unknown);
+    }
+
+    #[test]
+    fn detect_truncated_tar() {
+        assert_eq!(Format::detect(&tar_header(true)[..511]), Format::Unknown);
+    }
+
+    #[test]
+    fn detect_zip() {
+        assert_eq!(Format::detect(b"PK\x03\x04\x14\x00"), Format::Zip);
+        assert_eq!(Format::detect(b"PK\x05\x06\x00\x00"), Format::Zip);
+        assert_eq!(Format::detect(b"PK\x07\x08"), Format::Unknown);
+    }
+
+    #[test]
+    fn detect_sequence_files() {
+        assert_eq!(Format::detect(b">r1\nACGT\n"), Format::Fasta);
+        assert_eq!(Format::detect(b"\n \r\n\t>r1\nACGT\n"), Format::Fasta);
+        assert_eq!(Format::detect(b"@r1\nACGT\n+\nIIII\n"), Format::Fastq);
+        assert_eq!(Format::detect(b"\r\n@r1\nACGT\n+\nIIII\n"), Format::Fastq);
+    }
+
+    #[test]
+    fn detect_fasta_with_preamble() {
+        assert_eq!(Format::detect(b";comment\n>r1\nACGT\n"), Format::Fasta);
+        assert_eq!(Format::detect(b"ACGT\r\n>r1\nACGT\n"), Format::Fasta);
+    }
+
+    #[test]
+    fn detect_unknown() {
+        assert_eq!(Format::detect(b""), Format::Unknown);
+        assert_eq!(Format::detect(b" \n"), Format::Unknown);
+        assert_eq!(Format::detect(b"ACGT\nACGT\n"), Format::Unknown);
+        assert_eq!(Format::detect(b"ACGT>r1\n"), Format::Unknown);
+    }
+
+    #[tokio::test]
+    async fn peek_keeps_input() {
+        let input = b">r1\nACGT\n".as_slice();
+        let (prefix, mut reader) = peek(input, 4).await.unwrap();
+        assert_eq!(prefix, b">r1\n");
+
+        let mut all = Vec::new();
+        reader.read_to_end(&mut all).await.unwrap();
+        assert_eq!(all, input);
+    }
+}
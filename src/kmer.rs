@@ -0,0 +1,300 @@
+use std::str::FromStr;
+
+/// Returns the complement of a nucleotide.
+///
+/// Supports upper and lower case IUPAC codes.
+/// Characters without a complement are returned unchanged.
+pub fn complement(b: u8) -> u8 {
+    match b {
+        b'A' => b'T',
+        b'T' => b'A',
+        b'C' => b'G',
+        b'G' => b'C',
+        b'R' => b'Y',
+        b'Y' => b'R',
+        b'K' => b'M',
+        b'M' => b'K',
+        b'B' => b'V',
+        b'V' => b'B',
+        b'D' => b'H',
+        b'H' => b'D',
+        b'a' => b't',
+        b't' => b'a',
+        b'c' => b'g',
+        b'g' => b'c',
+        b'r' => b'y',
+        b'y' => b'r',
+        b'k' => b'm',
+        b'm' => b'k',
+        b'b' => b'v',
+        b'v' => b'b',
+        b'd' => b'h',
+        b'h' => b'd',
+        other => other,
+    }
+}
+
+/// Returns the lexicographically smaller of the k-mer and its reverse complement, in upper case.
+///
+/// Like Jellyfish and KMC, soft-masked lower case characters are identified with upper case ones.
+/// The buffer is used to store the result.
+pub fn canonical<'result>(kmer: &[u8], buffer: &'result mut Vec<u8>) -> &'result [u8] {
+    buffer.clear();
+    buffer.extend(
+        kmer.iter()
+            .rev()
+            .map(|&b| complement(b).to_ascii_uppercase()),
+    );
+
+    let forward = kmer.iter().map(u8::to_ascii_uppercase);
+    if forward.clone().lt(buffer.iter().copied()) {
+        buffer.clear();
+        buffer.extend(forward);
+    }
+    buffer
+}
+
+/// A set of characters that k-mers may consist of.
+#[derive(Debug, Clone)]
+pub struct Alphabet {
+    characters: [bool; 256],
+}
+
+impl Alphabet {
+    pub fn contains(&self, character: u8) -> bool {
+        self.characters[usize::from(character)]
+    }
+}
+
+impl FromStr for Alphabet {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err("the alphabet must not be empty".to_string());
+        }
+
+        let mut characters = [false; 256];
+        for character in s.bytes() {
+            characters[usize::from(character)] = true;
+        }
+        Ok(Self { characters })
+    }
+}
+
+/// An inclusive range of values of k, like `31`, `15-31` or `15-63:2`.
+#[derive(Debug, Clone)]
+pub struct KRange {
+    start: usize,
+    end: usize,
+    step: usize,
+}
+
+impl KRange {
+    pub fn iter(&self) -> impl Iterator<Item = usize> {
+        (self.start..=self.end).step_by(self.step)
+    }
+}
+
+impl FromStr for KRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse = |value: &str| {
+            value
+                .trim()
+                .parse::<usize>()
+                .map_err(|error| format!("invalid k '{value}': {error}"))
+        };
+
+        let (range, step) = match s.split_once(':') {
+            Some((range, step)) => (range, parse(step)?),
+            None => (s, 1),
+        };
+        let (start, end) = match range.split_once('-') {
+            Some((start, end)) => (parse(start)?, parse(end)?),
+            None => (parse(range)?, parse(range)?),
+        };
+
+        if start == 0 {
+            Err("k must not be zero".to_string())
+        } else if step == 0 {
+            Err("the step of a range of k must not be zero".to_string())
+        } else if start > end {
+            Err(format!("the range of k {start}-{end} is empty"))
+        } else {
+            Ok(Self { start, end, step })
+        }
+    }
+}
+
+/// A spaced seed like `1101011`, where only the characters at the `1` positions are part of a k-mer.
+#[derive(Debug, Clone)]
+pub struct SpacedSeed {
+    mask: Vec<bool>,
+}
+
+impl SpacedSeed {
+    /// The number of characters covered by the seed.
+    pub fn span(&self) -> usize {
+        self.mask.len()
+    }
+
+    /// The number of characters that are part of a k-mer.
+    pub fn weight(&self) -> usize {
+        self.mask.iter().filter(|&&used| used).count()
+    }
+
+    /// Returns the characters of the window at the `1` positions of the seed.
+    ///
+    /// The window must have the span of the seed.
+    pub fn extract<'result>(&self, window: &[u8], buffer: &'result mut Vec<u8>) -> &'result [u8] {
+        buffer.clear();
+        buffer.extend(self.select(window.iter().copied()));
+        buffer
+    }
+
+    /// Returns the lexicographically smaller of the k-mers of the window and of its reverse complement, in upper case.
+    ///
+    /// The reverse buffer is used to store the reverse complement of the window.
+    pub fn extract_canonical<'result>(
+        &self,
+        window: &[u8],
+        buffer: &'result mut Vec<u8>,
+        reverse_buffer: &mut Vec<u8>,
+    ) -> &'result [u8] {
+        self.extract(window, buffer);
+        buffer.make_ascii_uppercase();
+        reverse_buffer.clear();
+        reverse_buffer.extend(
+            window
+                .iter()
+                .rev()
+                .map(|&b| complement(b).to_ascii_uppercase()),
+        );
+
+        if self
+            .select(reverse_buffer.iter().copied())
+            .lt(buffer.iter().copied())
+        {
+            buffer.clear();
+            buffer.extend(self.select(reverse_buffer.iter().copied()));
+        }
+        buffer
+    }
+
+    /// Returns true if the characters of the window at the `1` positions of the seed are all in the alphabet.
+    pub fn selects_only(&self, window: &[u8], alphabet: &Alphabet) -> bool {
+        self.select(window.iter().copied())
+            .all(|character| alphabet.contains(character))
+    }
+
+    fn select(&self, window: impl Iterator<Item = u8>) -> impl Iterator<Item = u8> {
+        window
+            .zip(&self.mask)
+            .filter_map(|(character, &used)| used.then_some(character))
+    }
+}
+
+impl FromStr for SpacedSeed {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mask = s
+            .chars()
+            .map(|character| match character {
+                '1' => Ok(true),
+                '0' => Ok(false),
+                other => Err(format!(
+                    "invalid character '{other}' in spaced seed, only '0' and '1' are allowed"
+                )),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if mask.first() != Some(&true) || mask.last() != Some(&true) {
+            Err("a spaced seed must start and end with '1'".to_string())
+        } else {
+            Ok(Self { mask })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn k_values(range: &str) -> Result<Vec<usize>, String> {
+        range.parse::<KRange>().map(|range| range.iter().collect())
+    }
+
+    #[test]
+    fn k_range_single() {
+        assert_eq!(k_values("31"), Ok(vec![31]));
+    }
+
+    #[test]
+    fn k_range_inclusive() {
+        assert_eq!(k_values("15-19"), Ok(vec![15, 16, 17, 18, 19]));
+    }
+
+    #[test]
+    fn k_range_step() {
+        assert_eq!(k_values("15-23:2"), Ok(vec![15, 17, 19, 21, 23]));
+        assert_eq!(k_values("15-63:2").unwrap().len(), 25);
+        assert_eq!(k_values("15-20:4"), Ok(vec![15, 19]));
+    }
+
+    #[test]
+    fn k_range_invalid() {
+        assert!(k_values("0").is_err());
+        assert!(k_values("0-5").is_err());
+        assert!(k_values("15-63:0").is_err());
+        assert!(k_values("31-15").is_err());
+        assert!(k_values("abc").is_err());
+        assert!(k_values("15-").is_err());
+    }
+
+    #[test]
+    fn spaced_seed_mask() {
+        let seed: SpacedSeed = "1101".parse().unwrap();
+        assert_eq!(seed.span(), 4);
+        assert_eq!(seed.weight(), 3);
+        assert_eq!(seed.extract(b"ACGT", &mut Vec::new()), b"ACT");
+    }
+
+    #[test]
+    fn spaced_seed_canonical() {
+        // The seed is applied to the reverse complement `GCAA` of the window,
+        // instead of taking the reverse complement `GAA` of the forward k-mer `TTC`.
+        let seed: SpacedSeed = "1101".parse().unwrap();
+        let (mut buffer, mut reverse_buffer) = (Vec::new(), Vec::new());
+        assert_eq!(
+            seed.extract_canonical(b"TTGC", &mut buffer, &mut reverse_buffer),
+            b"GCA"
+        );
+        assert_eq!(
+            seed.extract_canonical(b"ttgc", &mut buffer, &mut reverse_buffer),
+            b"GCA"
+        );
+        assert_eq!(
+            seed.extract_canonical(b"AACG", &mut buffer, &mut reverse_buffer),
+            b"AAG"
+        );
+    }
+
+    #[test]
+    fn spaced_seed_alphabet() {
+        // Only the characters at the `1` positions must be in the alphabet.
+        let seed: SpacedSeed = "101".parse().unwrap();
+        let alphabet: Alphabet = "ACGT".parse().unwrap();
+        assert!(seed.selects_only(b"CNG", &alphabet));
+        assert!(!seed.selects_only(b"ACN", &alphabet));
+    }
+
+    #[test]
+    fn spaced_seed_invalid() {
+        assert!("0110".parse::<SpacedSeed>().is_err());
+        assert!("1a1".parse::<SpacedSeed>().is_err());
+        assert!("".parse::<SpacedSeed>().is_err());
+    }
+}
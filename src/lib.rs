@@ -0,0 +1,21 @@
+//! Count the k-mers of fasta and fastq files, possibly compressed and inside tar or zip archives.
+//!
+//! The entry point is [`KmerCounter`], which is configured by [`CountOptions`].
+
+pub mod async_file;
+pub mod compression;
+mod counter;
+pub mod counts;
+pub mod filter;
+pub mod format;
+pub mod kmer;
+pub mod minimizer;
+pub mod progress;
+pub mod report;
+pub mod skip;
+pub mod table;
+mod walk;
+
+pub use counter::{KmerCounter, STDIN_PATH};
+pub use counts::{CountOptions, KCounts, KmerCounts};
+pub use skip::{SkipReason, SkippedInput};
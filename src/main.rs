@@ -1,36 +1,147 @@
 use std::{
-    io::SeekFrom,
-    path::{Path, PathBuf},
-    pin::Pin,
+    io::{self, IsTerminal},
+    num::NonZeroUsize,
+    path::PathBuf,
+    sync::Arc,
 };
 
-use async_recursion::async_recursion;
 use clap::Parser;
-use log::{LevelFilter, debug};
-use simplelog::{TermLogger, TerminalMode};
-use tokio::{
-    fs::{File, ReadDir, read_dir},
-    io::{AsyncRead, AsyncReadExt, AsyncSeekExt, BufReader},
+use fasta_kmer_counter::{
+    CountOptions, KmerCounter,
+    filter::PathFilter,
+    kmer::{Alphabet, KRange, SpacedSeed},
+    minimizer::{MinimizerOptions, MinimizerOrder},
+    progress::{Progress, clear_progress, display_progress},
+    report::{ReportFormat, write_record_report, write_report},
+    table::{TableFormat, TableSort, table_path_for_k, write_histogram, write_table},
 };
-use tokio_stream::StreamExt;
-use tokio_tar::Archive;
-
-use crate::async_file::AsyncFile;
-
-mod async_file;
+use log::{LevelFilter, error};
+use simplelog::{TermLogger, TerminalMode};
 
 #[derive(clap::Parser)]
 struct Cli {
-    #[clap(short, long)]
-    k: usize,
+    /// The values of k, as a comma separated list of single values like `31`,
+    /// inclusive ranges like `15-31`, or ranges with a step like `15-63:2`.
+    /// All values are counted in a single pass over the input.
+    #[clap(
+        short,
+        long,
+        value_delimiter = ',',
+        required_unless_present = "seed",
+        conflicts_with = "seed"
+    )]
+    k: Vec<KRange>,
+
+    /// Count spaced k-mers of a seed like `1101011` instead of contiguous k-mers.
+    /// Only the characters at the `1` positions are part of a k-mer.
+    #[clap(long, conflicts_with = "minimizer_window")]
+    seed: Option<SpacedSeed>,
+
+    /// Count distinct k-mers instead of k-mer occurrences.
+    #[clap(long)]
+    distinct: bool,
+
+    /// Identify each k-mer with its reverse complement.
+    #[clap(long)]
+    canonical: bool,
+
+    /// Count (w,k)-minimizers instead of all k-mers, with this number w of consecutive k-mers per window.
+    /// Prints the number of minimizer positions and the number of distinct minimizers.
+    #[clap(long)]
+    minimizer_window: Option<NonZeroUsize>,
+
+    /// The order by which the minimizer of a window is selected.
+    #[clap(long, default_value = "lexicographic")]
+    minimizer_order: MinimizerOrder,
+
+    /// Only count k-mers that consist entirely of these characters, e.g. `ACGTacgt`.
+    /// Any other character, such as `N` or `-`, breaks the sequence.
+    /// By default, all characters are allowed.
+    #[clap(long)]
+    alphabet: Option<Alphabet>,
+
+    /// Write every k-mer with its occurrence count to this file.
+    #[clap(long)]
+    table: Option<PathBuf>,
+
+    /// The file format of the k-mer frequency table.
+    #[clap(long, default_value = "tsv")]
+    table_format: TableFormat,
+
+    /// The order of the rows of the k-mer frequency table.
+    #[clap(long, default_value = "kmer")]
+    table_sort: TableSort,
+
+    /// Write the k-mer abundance histogram to this file.
+    /// Each line contains an occurrence count and the number of distinct k-mers that occur that often.
+    #[clap(long)]
+    histogram: Option<PathBuf>,
+
+    /// The highest occurrence count in the histogram.
+    /// K-mers that occur more often are counted in its line.
+    #[clap(long, default_value = "10000", value_parser = clap::value_parser!(u64).range(1..))]
+    histogram_max: u64,
+
+    /// Write a report with the counts of each file, including files inside archives, to this file.
+    #[clap(long)]
+    report: Option<PathBuf>,
+
+    /// Write a report with the length and k-mer counts of each fasta or fastq record to this file.
+    #[clap(long)]
+    record_report: Option<PathBuf>,
+
+    /// The file format of the file and record reports.
+    #[clap(long, default_value = "tsv")]
+    report_format: ReportFormat,
+
+    /// The maximum nesting depth of archives inside archives.
+    /// Archives nested deeper are skipped.
+    #[clap(long, default_value = "4")]
+    max_archive_depth: usize,
+
+    /// Follow symlinks inside directories. This is the default.
+    #[clap(long, overrides_with = "no_follow_symlinks")]
+    follow_symlinks: bool,
+
+    /// Do not follow symlinks inside directories.
+    /// Symlinks given as input are still followed.
+    #[clap(long, overrides_with = "follow_symlinks")]
+    no_follow_symlinks: bool,
+
+    /// Only count files in directories and entries of archives that match this glob pattern, e.g. `*.fa*`.
+    /// A pattern without a `/` matches the file name, a pattern with a `/` matches the whole path,
+    /// where `**` matches any number of directories. Archives are always opened.
+    /// Can be given multiple times.
+    #[clap(long)]
+    include: Vec<String>,
+
+    /// Do not count files in directories and entries of archives that match this glob pattern, e.g. `**/tmp/**`.
+    /// Can be given multiple times.
+    #[clap(long)]
+    exclude: Vec<String>,
+
+    /// The maximum number of files to count at the same time.
+    /// Defaults to the number of available cores.
+    #[clap(short, long, default_value_t = default_jobs())]
+    jobs: usize,
+
+    /// Exit with a non-zero status if any input was skipped.
+    #[clap(long)]
+    strict: bool,
 
     #[clap(long, default_value = "info")]
     log_level: LevelFilter,
 
+    /// The files and directories to count.
+    /// `-` reads from standard input.
     #[clap(index = 1)]
     input: Vec<PathBuf>,
 }
 
+fn default_jobs() -> usize {
+    std::thread::available_parallelism().map_or(1, usize::from)
+}
+
 #[tokio::main]
 async fn main() {
     let cli = Cli::parse();
@@ -43,145 +154,149 @@ async fn main() {
     )
     .unwrap();
 
-    let mut total_count = 0;
-    for input in &cli.input {
-        total_count += count_path(input, cli.k).await;
-    }
-
-    println!("{total_count}");
-}
+    let filter = match PathFilter::new(&cli.include, &cli.exclude) {
+        Ok(filter) => filter,
+        Err(error) => {
+            error!("invalid glob pattern: {error}");
+            std::process::exit(1);
+        }
+    };
 
-async fn count_path(path: impl AsRef<Path>, k: usize) -> usize {
-    let path = path.as_ref();
+    let kmer_options = match cli.seed.clone() {
+        Some(seed) => CountOptions::with_seed(seed),
+        None => CountOptions::new(cli.k.iter().flat_map(KRange::iter).collect()),
+    };
+    let options = CountOptions {
+        extract_kmers: cli.distinct
+            || cli.table.is_some()
+            || cli.histogram.is_some()
+            || cli.minimizer_window.is_some(),
+        canonical: cli.canonical,
+        alphabet: cli.alphabet,
+        minimizer: cli.minimizer_window.map(|window| MinimizerOptions {
+            window: window.get(),
+            order: cli.minimizer_order,
+        }),
+        max_archive_depth: cli.max_archive_depth,
+        follow_symlinks: !cli.no_follow_symlinks,
+        progress: io::stderr()
+            .is_terminal()
+            .then(|| Arc::new(Progress::new())),
+        filter,
+        report_files: cli.report.is_some(),
+        report_records: cli.record_report.is_some(),
+        ..kmer_options
+    };
 
-    if let Ok(directory) = read_dir(path).await {
-        count_directory(path, directory, k).await
-    } else if let Ok(file) = File::open(path).await {
-        count_file(path, file, k).await
-    } else {
-        // If everything fails, just ignore it.
-        0
+    let counter = KmerCounter::new(options, cli.jobs);
+    // Only display the progress to humans, to keep log files clean.
+    let display = counter
+        .options()
+        .progress
+        .clone()
+        .map(|progress| tokio::spawn(display_progress(progress)));
+    let counts = counter.count_paths(cli.input.clone()).await;
+    if let Some(display) = display {
+        display.abort();
+        let _ = display.await;
+        clear_progress();
     }
-}
+    let options = counter.options();
 
-#[async_recursion]
-async fn count_directory(path: &Path, mut directory: ReadDir, k: usize) -> usize {
-    let mut sum = 0;
+    if let Some(report) = &cli.report
+        && let Err(error) = write_report(report, &options.k, &counts.files, cli.report_format).await
+    {
+        error!("could not write report {}: {error}", report.display());
+        std::process::exit(1);
+    }
 
-    while let Ok(Some(entry)) = directory.next_entry().await {
-        sum += count_path(entry.path(), k).await;
+    if let Some(report) = &cli.record_report
+        && let Err(error) = write_record_report(
+            report,
+            &options.k,
+            &counts.record_reports,
+            cli.report_format,
+        )
+        .await
+    {
+        error!(
+            "could not write record report {}: {error}",
+            report.display()
+        );
+        std::process::exit(1);
     }
 
-    debug!("directory {} contains {sum} {k}-mers", path.display());
-    sum
-}
+    if let Some(table) = &cli.table {
+        for counts in &counts.by_k {
+            let Some(kmers) = &counts.kmers else {
+                continue;
+            };
 
-#[allow(clippy::manual_unwrap_or_default, clippy::manual_unwrap_or)]
-async fn count_file(path: &Path, mut file: impl AsyncFile, k: usize) -> usize {
-    if let Some(amount) = count_tar_file(path, Box::new(&mut file), k).await {
-        amount
-    } else {
-        if file.seek(SeekFrom::Start(0)).await.is_err() {
-            return 0;
-        }
-        if let Some(amount) = count_fasta_file(path, &mut file, k).await {
-            amount
-        } else {
-            // If everything fails, just ignore it.
-            0
+            let table = if options.k.len() > 1 {
+                table_path_for_k(table, counts.k)
+            } else {
+                table.clone()
+            };
+            // The k-mers of a spaced seed are shorter than its span.
+            let kmer_length = options.seed.as_ref().map_or(counts.k, SpacedSeed::weight);
+            if let Err(error) =
+                write_table(&table, kmer_length, kmers, cli.table_format, cli.table_sort).await
+            {
+                error!("could not write k-mer table {}: {error}", table.display());
+                std::process::exit(1);
+            }
         }
     }
-}
 
-fn count_tar_file<'result>(
-    path: &'result Path,
-    file: Box<dyn 'result + AsyncFile>,
-    k: usize,
-) -> Pin<Box<dyn 'result + Future<Output = Option<usize>> + Send>> {
-    Box::pin(async move {
-        let mut archive = Archive::new(file);
-        let Ok(mut entries) = archive.entries() else {
-            debug!("file {} is not tar", path.display());
-            return None;
-        };
-        let mut sum = 0;
+    if let Some(histogram) = &cli.histogram {
+        for counts in &counts.by_k {
+            let Some(kmers) = &counts.kmers else {
+                continue;
+            };
 
-        while let Some(Ok(file)) = entries.next().await {
-            sum += count_fasta_file(&path.join(file.path().unwrap_or_default()), file, k)
-                .await
-                .unwrap_or_default();
+            let histogram = if options.k.len() > 1 {
+                table_path_for_k(histogram, counts.k)
+            } else {
+                histogram.clone()
+            };
+            if let Err(error) =
+                write_histogram(&histogram, kmers.values(), cli.histogram_max as usize).await
+            {
+                error!("could not write histogram {}: {error}", histogram.display());
+                std::process::exit(1);
+            }
         }
+    }
 
-        if sum == 0 {
-            // It is unclear how to discover if the file is actually a tar archive.
-            // So we simply say that if the tokio-tar crate finds no file, or no file that would be fasta, then it is not a tar file.
-            debug!("file {} is not tar", path.display());
-            None
+    for counts in &counts.by_k {
+        let count = if options.minimizer.is_some() {
+            format!(
+                "{}\t{}",
+                counts.total,
+                counts.distinct_count().unwrap_or_default()
+            )
+        } else if cli.distinct {
+            counts.distinct_count().unwrap_or_default().to_string()
         } else {
-            debug!("archive {} contains {sum} {k}-mers", path.display());
-            Some(sum)
-        }
-    })
-}
+            counts.total.to_string()
+        };
 
-async fn count_fasta_file(
-    path: &Path,
-    file: impl AsyncRead + Unpin + Send,
-    k: usize,
-) -> Option<usize> {
-    let mut reader = BufReader::with_capacity(1024 * 1024, file);
-    let mut sum = 0;
-
-    // Skip to entry header start.
-    let mut last_is_newline = true;
-    while let Ok(b) = reader.read_u8().await {
-        if last_is_newline && b == b'>' {
-            // Found entry header.
-            break;
+        if options.k.len() > 1 {
+            println!("{}\t{count}", counts.k);
+        } else {
+            println!("{count}");
         }
-
-        last_is_newline = b == b'\n' || b == b'\r';
     }
 
-    loop {
-        // Skip entry header.
-        let mut has_characters = false;
-        while let Ok(b) = reader.read_u8().await {
-            has_characters = true;
-            if b == b'\n' || b == b'\r' {
-                break;
-            }
+    if !counts.skipped.is_empty() {
+        // Print the summary independent of the log level, so that it is not hidden when `--strict` fails.
+        eprintln!("skipped {} inputs:", counts.skipped.len());
+        for skipped in &counts.skipped {
+            eprintln!("  {skipped}");
         }
 
-        if !has_characters {
-            break;
+        if cli.strict {
+            std::process::exit(1);
         }
-
-        // Count entry characters.
-        let mut character_count = 0usize;
-        let mut last_is_newline = true;
-        while let Ok(b) = reader.read_u8().await {
-            if b == b'>' {
-                if last_is_newline {
-                    // Found entry header.
-                    break;
-                } else {
-                    // Found entry header character  without preceding newline.
-                    debug!("file {} is not fasta", path.display());
-                    return None;
-                }
-            }
-
-            if b != b'\n' && b != b'\r' {
-                character_count += 1;
-            }
-
-            last_is_newline = b == b'\n' || b == b'\r';
-        }
-
-        sum += (character_count + 1).saturating_sub(k);
     }
-
-    debug!("file {} contains {sum} {k}-mers", path.display());
-    Some(sum)
 }
@@ -0,0 +1,170 @@
+use std::collections::VecDeque;
+
+use crate::kmer;
+
+/// Options for sampling (w,k)-minimizers instead of counting all k-mers.
+#[derive(Debug, Clone, Copy)]
+pub struct MinimizerOptions {
+    /// The number of consecutive k-mers in a window.
+    pub window: usize,
+
+    pub order: MinimizerOrder,
+}
+
+/// The order by which the minimizer of a window is selected.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum MinimizerOrder {
+    /// The lexicographically smallest k-mer.
+    #[default]
+    Lexicographic,
+
+    /// The k-mer with the smallest hash value, which avoids selecting runs of `A`s.
+    Hash,
+}
+
+/// A k-mer together with the key it is ordered by.
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+struct Key {
+    hash: u64,
+    kmer: Box<[u8]>,
+}
+
+/// Calls `minimizer` for each minimizer position of the sequence, in order, with the minimizer k-mer.
+///
+/// A position is reported once, even if it is the minimizer of several consecutive windows.
+/// If the sequence has fewer than `window` k-mers, the whole sequence is a single window.
+/// Of equal k-mers in a window, the leftmost one is selected.
+pub fn for_each_minimizer(
+    sequence: &[u8],
+    k: usize,
+    canonical: bool,
+    options: &MinimizerOptions,
+    mut minimizer: impl FnMut(&[u8]),
+) {
+    if k == 0 || sequence.len() < k {
+        return;
+    }
+
+    let kmer_count = sequence.len() - k + 1;
+    let window = options.window.clamp(1, kmer_count);
+    let mut buffer = Vec::with_capacity(k);
+    // The candidates for the minimizer of the current and later windows, with ascending keys.
+    let mut candidates: VecDeque<(usize, Key)> = VecDeque::new();
+    let mut last_position = None;
+
+    for (position, kmer) in sequence.windows(k).enumerate() {
+        let kmer = if canonical {
+            kmer::canonical(kmer, &mut buffer)
+        } else {
+            kmer
+        };
+        let key = Key {
+            hash: match options.order {
+                MinimizerOrder::Lexicographic => 0,
+                MinimizerOrder::Hash => hash(kmer),
+            },
+            kmer: kmer.into(),
+        };
+
+        // Keep equal k-mers, such that the leftmost one is selected.
+        while candidates.back().is_some_and(|(_, back)| *back > key) {
+            candidates.pop_back();
+        }
+        candidates.push_back((position, key));
+
+        if position + 1 < window {
+            continue;
+        }
+        while candidates
+            .front()
+            .is_some_and(|(front, _)| *front + window <= position)
+        {
+            candidates.pop_front();
+        }
+
+        let (minimizer_position, key) = candidates.front().unwrap();
+        if last_position != Some(*minimizer_position) {
+            last_position = Some(*minimizer_position);
+            minimizer(&key.kmer);
+        }
+    }
+}
+
+/// A 64-bit FNV-1a hash followed by the finalizer of MurmurHash3.
+///
+/// Unlike the hash of the standard library, it is the same on all platforms and versions.
+fn hash(kmer: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for &b in kmer {
+        hash ^= u64::from(b);
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xff51afd7ed558ccd);
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xc4ceb9fe1a85ec53);
+    hash ^ (hash >> 33)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn minimizers(sequence: &str, k: usize, window: usize, order: MinimizerOrder) -> Vec<String> {
+        let mut minimizers = Vec::new();
+        let options = MinimizerOptions { window, order };
+        for_each_minimizer(sequence.as_bytes(), k, false, &options, |kmer| {
+            minimizers.push(String::from_utf8(kmer.to_vec()).unwrap())
+        });
+        minimizers
+    }
+
+    #[test]
+    fn minimizer_tie_selects_leftmost() {
+        // The windows are `ACA` and `CAC`, selecting the `A` at positions 0 and 2.
+        // Selecting the rightmost `A` would report position 2 only once.
+        assert_eq!(
+            minimizers("ACAC", 1, 3, MinimizerOrder::Lexicographic),
+            ["A", "A"]
+        );
+    }
+
+    #[test]
+    fn minimizer_window_larger_than_sequence() {
+        assert_eq!(
+            minimizers("GATTACA", 3, 10, MinimizerOrder::Lexicographic),
+            ["ACA"]
+        );
+    }
+
+    #[test]
+    fn minimizer_position_reported_once() {
+        // The `A` at position 1 is the minimizer of the windows `CAG` and `AGT`.
+        assert_eq!(
+            minimizers("CAGTC", 1, 3, MinimizerOrder::Lexicographic),
+            ["A", "C"]
+        );
+    }
+
+    #[test]
+    fn minimizer_hash_order() {
+        let sequence = "AAACGTTGCA";
+        let smallest_hash = sequence
+            .as_bytes()
+            .windows(3)
+            .min_by_key(|kmer| hash(kmer))
+            .unwrap();
+        let smallest_hash = String::from_utf8(smallest_hash.to_vec()).unwrap();
+        assert_ne!(smallest_hash, "AAA");
+
+        assert_eq!(
+            minimizers(sequence, 3, 8, MinimizerOrder::Lexicographic),
+            ["AAA"]
+        );
+        assert_eq!(
+            minimizers(sequence, 3, 8, MinimizerOrder::Hash),
+            [smallest_hash]
+        );
+    }
+}
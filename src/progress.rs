@@ -0,0 +1,219 @@
+use std::{
+    fmt::{self, Display},
+    io,
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+    },
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use tokio::io::{AsyncRead, AsyncSeek, ReadBuf};
+
+use crate::async_file::AsyncFile;
+
+/// The progress of counting, shared between the counting tasks and a display.
+#[derive(Debug)]
+pub struct Progress {
+    start: Instant,
+
+    /// The number of input files that were counted completely.
+    files: AtomicUsize,
+
+    /// The number of input files found by walking the input paths.
+    total_files: AtomicUsize,
+
+    /// The number of bytes read from the input files, before decompression.
+    bytes: AtomicU64,
+
+    /// The sum of the sizes of the input files.
+    total_bytes: AtomicU64,
+
+    /// False if the size of some input file is unknown, e.g. because it is a pipe.
+    total_bytes_known: AtomicBool,
+
+    /// The file or archive entry that was started most recently.
+    current: Mutex<Option<PathBuf>>,
+}
+
+impl Progress {
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            files: AtomicUsize::new(0),
+            total_files: AtomicUsize::new(0),
+            bytes: AtomicU64::new(0),
+            total_bytes: AtomicU64::new(0),
+            total_bytes_known: AtomicBool::new(true),
+            current: Mutex::new(None),
+        }
+    }
+
+    /// Add an input file to count, with its size if it is known.
+    pub fn add_total_file(&self, size: Option<u64>) {
+        self.total_files.fetch_add(1, Ordering::Relaxed);
+        if let Some(size) = size {
+            self.total_bytes.fetch_add(size, Ordering::Relaxed);
+        } else {
+            self.total_bytes_known.store(false, Ordering::Relaxed);
+        }
+    }
+
+    pub fn finish_file(&self) {
+        self.files.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn add_bytes(&self, bytes: u64) {
+        self.bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub fn set_current(&self, path: &Path) {
+        *self.current.lock().unwrap() = Some(path.to_owned());
+    }
+
+    /// The estimated time until all input files are counted, if the sizes of all of them are known.
+    pub fn eta(&self) -> Option<Duration> {
+        if !self.total_bytes_known.load(Ordering::Relaxed) {
+            return None;
+        }
+
+        let bytes = self.bytes.load(Ordering::Relaxed);
+        let total_bytes = self.total_bytes.load(Ordering::Relaxed);
+        let throughput = self.throughput()?;
+        // Zip archives may be read partially more than once, so more bytes than the total may be read.
+        let remaining = total_bytes.saturating_sub(bytes);
+        Some(Duration::from_secs_f64(remaining as f64 / throughput))
+    }
+
+    /// The number of bytes read per second.
+    pub fn throughput(&self) -> Option<f64> {
+        let bytes = self.bytes.load(Ordering::Relaxed);
+        let elapsed = self.start.elapsed().as_secs_f64();
+        (bytes > 0 && elapsed > 0.0).then(|| bytes as f64 / elapsed)
+    }
+}
+
+impl Default for Progress {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Display for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}/{} files, {}",
+            self.files.load(Ordering::Relaxed),
+            self.total_files.load(Ordering::Relaxed),
+            Bytes(self.bytes.load(Ordering::Relaxed)),
+        )?;
+        if self.total_bytes_known.load(Ordering::Relaxed) {
+            write!(f, "/{}", Bytes(self.total_bytes.load(Ordering::Relaxed)))?;
+        }
+        if let Some(throughput) = self.throughput() {
+            write!(f, ", {}/s", Bytes(throughput as u64))?;
+        }
+        if let Some(eta) = self.eta() {
+            let seconds = eta.as_secs();
+            write!(
+                f,
+                ", ETA {}:{:02}:{:02}",
+                seconds / 3600,
+                seconds / 60 % 60,
+                seconds % 60
+            )?;
+        }
+        if let Some(current) = &*self.current.lock().unwrap() {
+            // Keep the end of long paths, which names the file or archive entry.
+            const MAX_LENGTH: usize = 60;
+            let current = current.to_string_lossy();
+            let characters = current.chars().count();
+            if characters > MAX_LENGTH {
+                let end: String = current.chars().skip(characters - MAX_LENGTH).collect();
+                write!(f, ", ...{end}")?;
+            } else {
+                write!(f, ", {current}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Redraw the progress on a single line of stderr until the task running this is aborted.
+pub async fn display_progress(progress: Arc<Progress>) {
+    let mut interval = tokio::time::interval(Duration::from_millis(250));
+    loop {
+        interval.tick().await;
+        eprint!("\r\x1b[K{progress}");
+    }
+}
+
+/// Clear the line of stderr on which the progress was displayed.
+pub fn clear_progress() {
+    eprint!("\r\x1b[K");
+}
+
+/// A number of bytes, displayed with a binary unit prefix.
+struct Bytes(u64);
+
+impl Display for Bytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const UNITS: [&str; 6] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB"];
+
+        let mut value = self.0 as f64;
+        let mut unit = 0;
+        while value >= 1024.0 && unit < UNITS.len() - 1 {
+            value /= 1024.0;
+            unit += 1;
+        }
+
+        if unit == 0 {
+            write!(f, "{} {}", self.0, UNITS[0])
+        } else {
+            write!(f, "{value:.1} {}", UNITS[unit])
+        }
+    }
+}
+
+/// A reader that records the number of bytes read from it in a [`Progress`].
+pub struct ProgressReader<R> {
+    inner: R,
+    progress: Option<Arc<Progress>>,
+}
+
+impl<R> ProgressReader<R> {
+    pub fn new(inner: R, progress: Option<Arc<Progress>>) -> Self {
+        Self { inner, progress }
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for ProgressReader<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let filled = buf.filled().len();
+        let result = Pin::new(&mut self.inner).poll_read(cx, buf);
+        if let Some(progress) = &self.progress {
+            progress.add_bytes((buf.filled().len() - filled) as u64);
+        }
+        result
+    }
+}
+
+impl<R: AsyncSeek + Unpin> AsyncSeek for ProgressReader<R> {
+    fn start_seek(mut self: Pin<&mut Self>, position: io::SeekFrom) -> io::Result<()> {
+        Pin::new(&mut self.inner).start_seek(position)
+    }
+
+    fn poll_complete(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        Pin::new(&mut self.inner).poll_complete(cx)
+    }
+}
+
+impl<F: AsyncFile> AsyncFile for ProgressReader<F> {}
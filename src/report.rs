@@ -0,0 +1,216 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use serde_json::{Map, Value, json};
+use tokio::{
+    fs::File,
+    io::{self, AsyncWriteExt, BufWriter},
+};
+
+use crate::{compression::Compression, counts::KmerCounts, format::Format};
+
+/// The counts of a single file on disk or inside an archive.
+#[derive(Debug)]
+pub struct FileReport {
+    pub path: PathBuf,
+    pub format: Format,
+    pub compression: Compression,
+    pub records: usize,
+    pub bases: usize,
+
+    /// The number of k-mer occurrences for each k.
+    pub kmers: Vec<(usize, usize)>,
+}
+
+/// The counts of a single fasta or fastq record.
+#[derive(Debug)]
+pub struct RecordReport {
+    /// The path of the file, shared by all of its records.
+    pub path: Arc<Path>,
+
+    /// The header up to the first whitespace.
+    pub id: String,
+
+    /// The number of sequence characters.
+    pub length: usize,
+
+    /// The number of k-mer occurrences for each k.
+    pub kmers: Vec<(usize, usize)>,
+}
+
+/// The file format of a file or record report.
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum ReportFormat {
+    Tsv,
+    Csv,
+    Json,
+}
+
+impl FileReport {
+    pub fn new(path: &Path, format: Format, compression: Compression, counts: &KmerCounts) -> Self {
+        Self {
+            path: path.to_owned(),
+            format,
+            compression,
+            records: counts.records,
+            bases: counts.bases,
+            kmers: counts
+                .by_k
+                .iter()
+                .map(|counts| (counts.k, counts.total))
+                .collect(),
+        }
+    }
+}
+
+impl RecordReport {
+    pub fn new(path: Arc<Path>, header: &[u8], length: usize, kmers: Vec<(usize, usize)>) -> Self {
+        let id = header
+            .split(|character| character.is_ascii_whitespace())
+            .next()
+            .unwrap_or_default();
+
+        Self {
+            path,
+            id: String::from_utf8_lossy(id).into_owned(),
+            length,
+            kmers,
+        }
+    }
+}
+
+/// Write one row per file to the given path.
+pub async fn write_report(
+    path: &Path,
+    k: &[usize],
+    files: &[FileReport],
+    format: ReportFormat,
+) -> io::Result<()> {
+    write_rows(
+        path,
+        k,
+        &["path", "format", "compression", "records", "bases"],
+        files.iter().map(|file| {
+            (
+                vec![
+                    json!(file.path.to_string_lossy()),
+                    json!(file.format.to_string()),
+                    json!(file.compression.to_string()),
+                    json!(file.records),
+                    json!(file.bases),
+                ],
+                file.kmers.as_slice(),
+            )
+        }),
+        format,
+    )
+    .await
+}
+
+/// Write one row per record to the given path.
+pub async fn write_record_report(
+    path: &Path,
+    k: &[usize],
+    records: &[RecordReport],
+    format: ReportFormat,
+) -> io::Result<()> {
+    write_rows(
+        path,
+        k,
+        &["path", "id", "length"],
+        records.iter().map(|record| {
+            (
+                vec![
+                    json!(record.path.to_string_lossy()),
+                    json!(record.id),
+                    json!(record.length),
+                ],
+                record.kmers.as_slice(),
+            )
+        }),
+        format,
+    )
+    .await
+}
+
+/// Write rows consisting of the given columns followed by the k-mer counts for each k.
+///
+/// In JSON, the k-mer counts are written as an object that maps each k to its count.
+async fn write_rows<'row>(
+    path: &Path,
+    k: &[usize],
+    columns: &[&str],
+    rows: impl Iterator<Item = (Vec<Value>, &'row [(usize, usize)])>,
+    format: ReportFormat,
+) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path).await?);
+
+    match format {
+        ReportFormat::Tsv | ReportFormat::Csv => {
+            let separator = if matches!(format, ReportFormat::Tsv) {
+                "\t"
+            } else {
+                ","
+            };
+
+            let mut header: Vec<_> = columns.iter().map(|column| column.to_string()).collect();
+            header.extend(k.iter().map(|k| format!("{k}-mers")));
+            writer
+                .write_all(format!("{}\n", header.join(separator)).as_bytes())
+                .await?;
+
+            for (fields, kmers) in rows {
+                let mut row: Vec<_> = fields
+                    .into_iter()
+                    .map(|field| match field {
+                        Value::String(field) if matches!(format, ReportFormat::Csv) => {
+                            csv_escape(&field)
+                        }
+                        Value::String(field) => field,
+                        field => field.to_string(),
+                    })
+                    .collect();
+                row.extend(kmers.iter().map(|(_, count)| count.to_string()));
+                writer
+                    .write_all(format!("{}\n", row.join(separator)).as_bytes())
+                    .await?;
+            }
+        }
+        ReportFormat::Json => {
+            let rows: Vec<_> = rows
+                .map(|(fields, kmers)| {
+                    let mut row: Map<_, _> = columns
+                        .iter()
+                        .map(|column| column.to_string())
+                        .zip(fields)
+                        .collect();
+                    row.insert(
+                        "kmers".to_string(),
+                        kmers
+                            .iter()
+                            .map(|(k, count)| (k.to_string(), json!(count)))
+                            .collect::<Map<_, _>>()
+                            .into(),
+                    );
+                    row
+                })
+                .collect();
+            let json = serde_json::to_string_pretty(&rows)?;
+            writer.write_all(json.as_bytes()).await?;
+            writer.write_all(b"\n").await?;
+        }
+    }
+
+    writer.flush().await
+}
+
+/// Quote a CSV field if it contains special characters.
+fn csv_escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
@@ -0,0 +1,80 @@
+use std::{
+    error::Error,
+    fmt::{self, Display},
+    io,
+    path::PathBuf,
+};
+
+use crate::format::Format;
+
+/// An input that was skipped instead of counted.
+#[derive(Debug)]
+pub struct SkippedInput {
+    pub path: PathBuf,
+    pub reason: SkipReason,
+}
+
+/// The reason why an input was skipped.
+#[derive(Debug)]
+pub enum SkipReason {
+    /// The input could not be read.
+    Io(io::Error),
+
+    /// The format of the input was not recognised.
+    UnknownFormat,
+
+    /// The input does not follow its detected format.
+    Malformed { format: Format, message: String },
+
+    /// The input is an archive nested deeper than the maximum archive depth.
+    TooDeeplyNested { max_archive_depth: usize },
+
+    /// The input is a symlink to a directory that contains it.
+    SymlinkLoop { ancestor: PathBuf },
+}
+
+impl SkipReason {
+    pub fn malformed(format: Format, message: impl ToString) -> Self {
+        Self::Malformed {
+            format,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl From<io::Error> for SkipReason {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "{error}"),
+            Self::UnknownFormat => write!(f, "unknown format"),
+            Self::Malformed { format, message } => write!(f, "malformed {format}: {message}"),
+            Self::TooDeeplyNested { max_archive_depth } => {
+                write!(f, "archive nested deeper than {max_archive_depth} archives")
+            }
+            Self::SymlinkLoop { ancestor } => {
+                write!(f, "symlink loop back to {}", ancestor.display())
+            }
+        }
+    }
+}
+
+impl Error for SkipReason {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl Display for SkippedInput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.reason)
+    }
+}
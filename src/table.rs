@@ -0,0 +1,104 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use tokio::{
+    fs::File,
+    io::{self, AsyncWriteExt, BufWriter},
+};
+
+/// The file format of a k-mer frequency table.
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum TableFormat {
+    /// One line per k-mer, containing the k-mer and its count separated by a tab.
+    Tsv,
+
+    /// The k as little endian u64, followed by one record per k-mer.
+    /// Each record is the k-mer followed by its count as little endian u64.
+    Binary,
+}
+
+/// The order of the rows of a k-mer frequency table.
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum TableSort {
+    /// Sort lexicographically by k-mer.
+    Kmer,
+
+    /// Sort by descending count, and lexicographically by k-mer for equal counts.
+    Count,
+}
+
+/// Write a k-mer frequency table to the given path.
+pub async fn write_table<'kmer>(
+    path: &Path,
+    k: usize,
+    kmers: impl IntoIterator<Item = (&'kmer Box<[u8]>, &'kmer usize)>,
+    format: TableFormat,
+    sort: TableSort,
+) -> io::Result<()> {
+    let mut kmers: Vec<_> = kmers.into_iter().collect();
+    match sort {
+        TableSort::Kmer => kmers.sort_unstable(),
+        TableSort::Count => kmers.sort_unstable_by(|(kmer_a, count_a), (kmer_b, count_b)| {
+            count_b.cmp(count_a).then_with(|| kmer_a.cmp(kmer_b))
+        }),
+    }
+
+    let mut writer = BufWriter::with_capacity(1024 * 1024, File::create(path).await?);
+
+    match format {
+        TableFormat::Tsv => {
+            for (kmer, count) in kmers {
+                writer.write_all(kmer).await?;
+                writer.write_all(format!("\t{count}\n").as_bytes()).await?;
+            }
+        }
+        TableFormat::Binary => {
+            writer.write_u64_le(k as u64).await?;
+            for (kmer, count) in kmers {
+                writer.write_all(kmer).await?;
+                writer.write_u64_le(*count as u64).await?;
+            }
+        }
+    }
+
+    writer.flush().await
+}
+
+/// Write the k-mer abundance histogram to the given path.
+///
+/// Each line contains an occurrence count and the number of distinct k-mers with that count, separated by a tab.
+/// K-mers that occur at least `max_count` times are counted in the line of `max_count`.
+/// Counts without k-mers are omitted.
+pub async fn write_histogram<'kmer>(
+    path: &Path,
+    counts: impl IntoIterator<Item = &'kmer usize>,
+    max_count: usize,
+) -> io::Result<()> {
+    let mut histogram = BTreeMap::new();
+    for &count in counts {
+        *histogram.entry(count.min(max_count)).or_insert(0usize) += 1;
+    }
+
+    let mut writer = BufWriter::new(File::create(path).await?);
+    for (count, kmers) in histogram {
+        writer
+            .write_all(format!("{count}\t{kmers}\n").as_bytes())
+            .await?;
+    }
+    writer.flush().await
+}
+
+/// Returns the path of the k-mer frequency table for a single k, if tables for several k are written.
+///
+/// The k is inserted before the file extension, e.g. `table.tsv` becomes `table.k31.tsv`.
+pub fn table_path_for_k(path: &Path, k: usize) -> PathBuf {
+    let mut file_name = path.file_stem().unwrap_or_default().to_os_string();
+    file_name.push(format!(".k{k}"));
+    if let Some(extension) = path.extension() {
+        file_name.push(".");
+        file_name.push(extension);
+    }
+    path.with_file_name(file_name)
+}
@@ -0,0 +1,270 @@
+use std::{
+    collections::HashMap,
+    fs::Metadata,
+    path::{Path, PathBuf},
+};
+
+use async_recursion::async_recursion;
+use log::debug;
+use tokio::fs::{metadata, read_dir, symlink_metadata};
+
+use crate::{counter::STDIN_PATH, counts::CountOptions, skip::SkipReason};
+
+/// Identifies a file or directory independent of the path it was reached by.
+type FileId = (u64, u64);
+
+/// An input found by walking the input paths.
+#[derive(Debug)]
+pub enum WalkEntry {
+    /// A file to count.
+    File {
+        path: PathBuf,
+
+        /// The size of the file, if it is known.
+        size: Option<u64>,
+
+        /// True if the path was given as input instead of being found in a directory.
+        is_input: bool,
+    },
+
+    /// An input that cannot be counted.
+    Skipped(PathBuf, SkipReason),
+}
+
+/// Walks the input paths in order, resolving directories into the files they contain.
+///
+/// Each file and directory is visited only once, even if it is reachable by several paths.
+pub struct Walk<'options> {
+    options: &'options CountOptions,
+    entries: Vec<WalkEntry>,
+
+    /// The first path by which each file and directory was visited.
+    visited: HashMap<FileId, PathBuf>,
+}
+
+impl<'options> Walk<'options> {
+    pub fn new(options: &'options CountOptions) -> Self {
+        Self {
+            options,
+            entries: Vec::new(),
+            visited: HashMap::new(),
+        }
+    }
+
+    /// Walk the given input paths and return the files to count, in order.
+    pub async fn walk(mut self, paths: Vec<PathBuf>) -> Vec<WalkEntry> {
+        for path in paths {
+            self.walk_path(path, true, &mut Vec::new()).await;
+        }
+        self.entries
+    }
+
+    /// Walk a single path.
+    ///
+    /// Paths given on the command line are always followed, even if they are symlinks.
+    /// The ancestors are the directories that contain the path, to detect symlink loops.
+    #[async_recursion]
+    async fn walk_path(
+        &mut self,
+        path: PathBuf,
+        is_input: bool,
+        ancestors: &mut Vec<(FileId, PathBuf)>,
+    ) {
+        if is_input && path == Path::new(STDIN_PATH) {
+            self.entries.push(WalkEntry::File {
+                path,
+                size: None,
+                is_input,
+            });
+            return;
+        }
+
+        let metadata = if is_input || self.options.follow_symlinks {
+            metadata(&path).await
+        } else {
+            symlink_metadata(&path).await
+        };
+        let metadata = match metadata {
+            Ok(metadata) => metadata,
+            Err(error) => {
+                self.entries.push(WalkEntry::Skipped(path, error.into()));
+                return;
+            }
+        };
+
+        if metadata.is_symlink() {
+            debug!("not following symlink {}", path.display());
+            return;
+        }
+        // The include patterns are applied when counting, to only select files that are not archives.
+        if !is_input && self.options.filter.is_excluded(&path) {
+            debug!("{} is excluded", path.display());
+            return;
+        }
+        // Named pipes and devices may block when they are read to detect archives.
+        if !is_input
+            && !metadata.is_dir()
+            && !metadata.is_file()
+            && !self.options.filter.is_included(&path)
+        {
+            debug!("{} is not included", path.display());
+            return;
+        }
+
+        if let Some(id) = file_id(&metadata) {
+            if let Some((_, ancestor)) = ancestors.iter().find(|(ancestor, _)| *ancestor == id) {
+                let ancestor = ancestor.clone();
+                self.entries.push(WalkEntry::Skipped(
+                    path,
+                    SkipReason::SymlinkLoop { ancestor },
+                ));
+                return;
+            }
+            if let Some(first) = self.visited.get(&id) {
+                debug!(
+                    "{} was already visited as {}",
+                    path.display(),
+                    first.display()
+                );
+                return;
+            }
+            self.visited.insert(id, path.clone());
+
+            if metadata.is_dir() {
+                ancestors.push((id, path.clone()));
+                self.walk_directory(path, ancestors).await;
+                ancestors.pop();
+                return;
+            }
+        } else if metadata.is_dir() {
+            self.walk_directory(path, ancestors).await;
+            return;
+        }
+
+        // Named pipes have a size of zero, even if data is written to them.
+        let size = metadata.is_file().then_some(metadata.len());
+        self.entries.push(WalkEntry::File {
+            path,
+            size,
+            is_input,
+        });
+    }
+
+    async fn walk_directory(&mut self, path: PathBuf, ancestors: &mut Vec<(FileId, PathBuf)>) {
+        let mut directory = match read_dir(&path).await {
+            Ok(directory) => directory,
+            Err(error) => {
+                self.entries.push(WalkEntry::Skipped(path, error.into()));
+                return;
+            }
+        };
+
+        let mut paths = Vec::new();
+        let mut error = None;
+        loop {
+            match directory.next_entry().await {
+                Ok(Some(entry)) => paths.push(entry.path()),
+                Ok(None) => break,
+                Err(read_error) => {
+                    error = Some(read_error);
+                    break;
+                }
+            }
+        }
+        drop(directory);
+        // Sort the entries so that the report does not depend on the order of the file system.
+        paths.sort_unstable();
+
+        for entry_path in paths {
+            self.walk_path(entry_path, false, ancestors).await;
+        }
+        if let Some(error) = error {
+            self.entries.push(WalkEntry::Skipped(path, error.into()));
+        }
+    }
+}
+
+#[cfg(unix)]
+fn file_id(metadata: &Metadata) -> Option<FileId> {
+    use std::os::unix::fs::MetadataExt;
+
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_id(_metadata: &Metadata) -> Option<FileId> {
+    None
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::{
+        fs::{create_dir, hard_link, write},
+        os::unix::fs::symlink,
+    };
+
+    use super::*;
+
+    async fn walk(paths: &[&Path], options: &CountOptions) -> Vec<WalkEntry> {
+        Walk::new(options)
+            .walk(paths.iter().map(|path| path.to_path_buf()).collect())
+            .await
+    }
+
+    fn file_paths(entries: &[WalkEntry]) -> Vec<&Path> {
+        entries
+            .iter()
+            .filter_map(|entry| match entry {
+                WalkEntry::File { path, .. } => Some(path.as_path()),
+                WalkEntry::Skipped(..) => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn symlink_loop() {
+        let directory = tempfile::tempdir().unwrap();
+        let root = directory.path();
+        write(root.join("a.fa"), ">r1\nACGT\n").unwrap();
+        create_dir(root.join("sub")).unwrap();
+        symlink(root, root.join("sub/loop")).unwrap();
+
+        let options = CountOptions::new(vec![3]);
+        let entries = walk(&[root], &options).await;
+        assert_eq!(file_paths(&entries), [root.join("a.fa")]);
+        assert!(
+            matches!(
+                &entries[..],
+                [
+                    WalkEntry::File { .. },
+                    WalkEntry::Skipped(path, SkipReason::SymlinkLoop { ancestor }),
+                ] if *path == root.join("sub/loop") && ancestor == root
+            ),
+            "{entries:?}"
+        );
+
+        // Without following symlinks, the loop is not entered.
+        let options = CountOptions {
+            follow_symlinks: false,
+            ..CountOptions::new(vec![3])
+        };
+        let entries = walk(&[root], &options).await;
+        assert_eq!(file_paths(&entries), [root.join("a.fa")]);
+        assert_eq!(entries.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn duplicates() {
+        let directory = tempfile::tempdir().unwrap();
+        let root = directory.path();
+        write(root.join("a.fa"), ">r1\nACGT\n").unwrap();
+        hard_link(root.join("a.fa"), root.join("b.fa")).unwrap();
+        symlink(root.join("a.fa"), root.join("c.fa")).unwrap();
+        write(root.join("d.fa"), ">r1\nACGT\n").unwrap();
+
+        let options = CountOptions::new(vec![3]);
+        let entries = walk(&[&root.join("d.fa"), root, &root.join("b.fa")], &options).await;
+        assert_eq!(file_paths(&entries), [root.join("d.fa"), root.join("a.fa")]);
+        assert_eq!(entries.len(), 2);
+    }
+}